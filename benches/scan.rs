@@ -0,0 +1,168 @@
+//! Compares scanning a synthetic Songs folder with the header-only parallel reader against
+//! the baseline scan, which read every .osu in full one after another looking for its
+//! `BeatmapID:` line. Both are also run hashing every uploaded difficulty afterwards, as a
+//! check does when the API reports a checksum for each of them, the most a scan can cost.
+//!
+//! Every iteration starts from a cold page cache: the folder's files are evicted from it
+//! first, so the numbers include the disk reads the header-only reader is meant to save.
+//! The never uploaded difficulties (one folder in `UNSUBMITTED_EVERY` here) are never hashed.
+//!
+//! `cargo bench --bench scan`
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use osu_unsubmitted_extractor::{song_folder::Scan, SongsScanner};
+
+const FOLDERS: usize = 200;
+const DIFFICULTIES_PER_FOLDER: usize = 5;
+const HIT_OBJECTS_PER_DIFFICULTY: usize = 1500;
+const UNSUBMITTED_EVERY: usize = 4;
+
+fn synthetic_songs_folder() -> PathBuf {
+    let songs = std::env::temp_dir().join(format!("scan-bench-{}", std::process::id()));
+    let mut hit_objects = String::new();
+    for i in 0..HIT_OBJECTS_PER_DIFFICULTY {
+        hit_objects.push_str(&format!(
+            "{},{},{},2,0,B|200:200|300:100,1,140,2|0,0:0|1:2,0:0:0:0:\n",
+            i % 512,
+            i % 384,
+            i * 100
+        ));
+    }
+    for folder in 0..FOLDERS {
+        let dir = songs.join(format!("{folder} Artist - Title"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("audio.mp3"), []).unwrap();
+        let unsubmitted = folder % UNSUBMITTED_EVERY == 0;
+        for difficulty in 0..DIFFICULTIES_PER_FOLDER {
+            let (id, set_id) = if unsubmitted {
+                (0, -1)
+            } else {
+                (
+                    (folder * DIFFICULTIES_PER_FOLDER + difficulty + 1) as i64,
+                    folder as i64,
+                )
+            };
+            let contents = format!(
+                "osu file format v14\n\n[General]\nAudioFilename: audio.mp3\n\n\
+                 [Metadata]\nTitle:Title\nArtist:Artist\nCreator:Mapper\nVersion:Diff {difficulty}\n\
+                 BeatmapID:{id}\nBeatmapSetID:{set_id}\n\n\
+                 [Difficulty]\nHPDrainRate:5\nCircleSize:4\nOverallDifficulty:8\nApproachRate:9\n\
+                 SliderMultiplier:1.8\nSliderTickRate:1\n\n\
+                 [Events]\n0,0,\"bg.jpg\",0,0\n\n\
+                 [TimingPoints]\n0,300,4,2,1,60,1,0\n\n\
+                 [HitObjects]\n{hit_objects}"
+            );
+            fs::write(dir.join(format!("diff {difficulty}.osu")), contents).unwrap();
+        }
+    }
+    songs
+}
+
+/// Drops the folder's files from the page cache so the next read has to go to the disk.
+#[cfg(unix)]
+fn evict_from_page_cache(songs: &Path) {
+    use std::os::fd::AsRawFd;
+
+    for folder in fs::read_dir(songs).unwrap() {
+        for file in fs::read_dir(folder.unwrap().path()).unwrap() {
+            let file = fs::File::open(file.unwrap().path()).unwrap();
+            // Dirty pages can't be dropped, write them out first.
+            file.sync_all().unwrap();
+            // SAFETY: the descriptor stays open for the duration of the call.
+            unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn evict_from_page_cache(_songs: &Path) {
+    eprintln!("Can't evict files from the page cache here, reads will be warm");
+}
+
+/// The scan before header-only reading: every .osu read into a string, in turn, for the
+/// `BeatmapID:` line. With `hash`, the uploaded ones are also hashed while they're in memory.
+fn baseline_scan(songs: &Path, hash: bool) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for folder in fs::read_dir(songs).unwrap() {
+        for file in fs::read_dir(folder.unwrap().path()).unwrap() {
+            let path = file.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "osu") {
+                continue;
+            }
+            let contents = fs::read_to_string(&path).unwrap();
+            let id = contents.lines().find_map(|line| {
+                line.strip_prefix("BeatmapID:")?
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|id| *id != 0)
+            });
+            if hash && id.is_some() {
+                let _ = md5::compute(contents.as_bytes());
+            }
+            ids.extend(id);
+        }
+    }
+    ids
+}
+
+/// Hashes every uploaded difficulty the scan found, like a check does when the API reports
+/// a checksum for each of them.
+fn hash_uploaded(scan: Scan) -> usize {
+    scan.song_folders
+        .iter()
+        .flat_map(|folder| &folder.difficulties)
+        .filter(|d| d.metadata.beatmap_id.is_some_and(|id| id > 0))
+        .filter_map(|d| d.md5())
+        .count()
+}
+
+fn scan(c: &mut Criterion) {
+    let songs = synthetic_songs_folder();
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+    group.bench_function("baseline_sequential", |b| {
+        b.iter_batched(
+            || evict_from_page_cache(&songs),
+            |()| baseline_scan(&songs, false),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("baseline_sequential_md5", |b| {
+        b.iter_batched(
+            || evict_from_page_cache(&songs),
+            |()| baseline_scan(&songs, true),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("header_only_1_worker", |b| {
+        b.iter_batched(
+            || evict_from_page_cache(&songs),
+            |()| SongsScanner::new(&songs).workers(1).scan(),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("header_only_parallel", |b| {
+        b.iter_batched(
+            || evict_from_page_cache(&songs),
+            |()| SongsScanner::new(&songs).scan(),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("header_only_parallel_md5", |b| {
+        b.iter_batched(
+            || evict_from_page_cache(&songs),
+            |()| hash_uploaded(SongsScanner::new(&songs).scan().unwrap()),
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+    fs::remove_dir_all(&songs).unwrap();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
@@ -0,0 +1,378 @@
+use std::{collections::HashMap, str::FromStr};
+
+use futures_util::{stream, StreamExt};
+use reqwest::Url;
+use secrecy::SecretString;
+
+use crate::{
+    rate_limit::RateLimiter,
+    retry::{send_paced_with_retry, RetryPolicy},
+    spec::web::{Beatmap, GetBeatmapsResponse, GetBeatmapsetResponse},
+    submission::{SetStatus, SubmissionStatus},
+    token::TokenManager,
+};
+
+pub const TOKEN_URL: &str = "https://osu.ppy.sh/oauth/token";
+pub const BEATMAPS_URL: &str = "https://osu.ppy.sh/api/v2/beatmaps";
+pub const BEATMAPSETS_URL: &str = "https://osu.ppy.sh/api/v2/beatmapsets";
+
+/// The most IDs `/beatmaps` accepts in one request.
+const LOOKUP_CHUNK_SIZE: usize = 50;
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u64 = 50;
+const DEFAULT_BURST: u32 = 5;
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// The osu! API v2 endpoints the extractor needs, paced and retried.
+pub struct OsuApiClient {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    tokens: TokenManager,
+    beatmaps_url: Url,
+    beatmapsets_url: Url,
+    unknown_retry_passes: u32,
+    rate_limit_per_minute: u64,
+    burst: u32,
+    max_concurrent_requests: usize,
+    limiter: RateLimiter,
+}
+
+impl OsuApiClient {
+    /// Obtains a client credentials token, renewed as needed from then on. `None` if that
+    /// failed, the reason is logged.
+    pub async fn authenticate(
+        client_id: u64,
+        secret: &SecretString,
+        retry: RetryPolicy,
+    ) -> Option<Self> {
+        let client = reqwest::Client::new();
+        let tokens = TokenManager::authenticate(
+            client.clone(),
+            retry.clone(),
+            Url::from_str(TOKEN_URL).expect("legit url"),
+            client_id,
+            secret,
+        )
+        .await?;
+        Some(Self::with_tokens(client, tokens, retry))
+    }
+
+    /// For callers that already hold a token.
+    pub fn with_token(token: String, retry: RetryPolicy) -> Self {
+        let client = reqwest::Client::new();
+        let tokens = TokenManager::fixed(client.clone(), retry.clone(), token);
+        Self::with_tokens(client, tokens, retry)
+    }
+
+    fn with_tokens(client: reqwest::Client, tokens: TokenManager, retry: RetryPolicy) -> Self {
+        Self {
+            client,
+            retry,
+            tokens,
+            beatmaps_url: Url::from_str(BEATMAPS_URL).expect("legit url"),
+            beatmapsets_url: Url::from_str(BEATMAPSETS_URL).expect("legit url"),
+            unknown_retry_passes: 1,
+            rate_limit_per_minute: DEFAULT_RATE_LIMIT_PER_MINUTE,
+            burst: DEFAULT_BURST,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            limiter: RateLimiter::new(DEFAULT_RATE_LIMIT_PER_MINUTE, DEFAULT_BURST),
+        }
+    }
+
+    pub fn beatmaps_url(mut self, url: Url) -> Self {
+        self.beatmaps_url = url;
+        self
+    }
+
+    pub fn beatmapsets_url(mut self, url: Url) -> Self {
+        self.beatmapsets_url = url;
+        self
+    }
+
+    pub fn rate_limit_per_minute(mut self, rate_limit_per_minute: u64) -> Self {
+        self.rate_limit_per_minute = rate_limit_per_minute;
+        self.limiter = RateLimiter::new(self.rate_limit_per_minute, self.burst);
+        self
+    }
+
+    /// How many requests may go out back to back before the rate limit kicks in.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self.limiter = RateLimiter::new(self.rate_limit_per_minute, self.burst);
+        self
+    }
+
+    /// How many `/beatmaps` or `/beatmapsets` requests may be in flight at once.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = max.max(1);
+        self
+    }
+
+    /// How many extra passes to make over IDs the API couldn't answer for.
+    pub fn unknown_retry_passes(mut self, passes: u32) -> Self {
+        self.unknown_retry_passes = passes;
+        self
+    }
+
+    /// API requests sent so far, retries included, not counting authentication.
+    pub fn requests_made(&self) -> usize {
+        self.limiter.acquired()
+    }
+
+    /// Looks up `ids` in chunks of 50, one request per chunk with several in flight at once,
+    /// then retries the `Unknown` ones. `on_chunk` sees each chunk's answers as they arrive,
+    /// not necessarily in order.
+    pub async fn lookup_submission_statuses(
+        &self,
+        ids: &[u64],
+        mut on_chunk: impl FnMut(&HashMap<u64, SubmissionStatus>),
+    ) -> HashMap<u64, SubmissionStatus> {
+        let mut statuses = self.lookup_pass(ids, &mut on_chunk).await;
+        for pass in 1..=self.unknown_retry_passes {
+            let unknown_ids: Vec<u64> = statuses
+                .iter()
+                .filter(|(_, status)| status.is_unknown())
+                .map(|(id, _)| *id)
+                .collect();
+            if unknown_ids.is_empty() {
+                break;
+            }
+            log::warn!(
+                "Retrying {} IDs the API couldn't answer for (pass {pass}/{})",
+                unknown_ids.len(),
+                self.unknown_retry_passes
+            );
+            let retried = self.lookup_pass(&unknown_ids, &mut on_chunk).await;
+            statuses.extend(retried);
+        }
+        statuses
+    }
+
+    async fn lookup_pass(
+        &self,
+        ids: &[u64],
+        on_chunk: &mut impl FnMut(&HashMap<u64, SubmissionStatus>),
+    ) -> HashMap<u64, SubmissionStatus> {
+        let mut responses = stream::iter(ids.chunks(LOOKUP_CHUNK_SIZE).enumerate())
+            .map(|(chunk_index, chunk)| async move {
+                log::info!(
+                    "[{0}/{1}] Checking id {0} to {2}.",
+                    chunk_index * LOOKUP_CHUNK_SIZE + 1,
+                    ids.len(),
+                    chunk.len() + chunk_index * LOOKUP_CHUNK_SIZE,
+                );
+                (chunk, self.fetch_submitted_beatmaps(chunk).await)
+            })
+            .buffer_unordered(self.max_concurrent_requests);
+
+        let mut statuses = HashMap::new();
+        while let Some((chunk, found)) = responses.next().await {
+            let chunk_statuses: HashMap<u64, SubmissionStatus> = match found {
+                Some(mut found) => chunk
+                    .iter()
+                    .map(|id| {
+                        let status = match found.remove(id) {
+                            Some(beatmap) => SubmissionStatus::Submitted(beatmap),
+                            None => SubmissionStatus::NotFound,
+                        };
+                        (*id, status)
+                    })
+                    .collect(),
+                None => chunk
+                    .iter()
+                    .map(|id| (*id, SubmissionStatus::Unknown))
+                    .collect(),
+            };
+            on_chunk(&chunk_statuses);
+            statuses.extend(chunk_statuses);
+        }
+        statuses
+    }
+
+    /// Returns the server-side details for each submitted ID, IDs missing from the map weren't found.
+    /// `None` if the request failed, in which case nothing can be said about any of the IDs.
+    pub async fn fetch_submitted_beatmaps(&self, ids: &[u64]) -> Option<HashMap<u64, Beatmap>> {
+        let query: Vec<(String, String)> = ids
+            .iter()
+            .map(|id| ("ids[]".to_owned(), id.to_string()))
+            .collect();
+
+        let res = self
+            .send_authorized(|| self.client.get(self.beatmaps_url.clone()).query(&query))
+            .await;
+
+        let res = match res {
+            Ok(res) => res,
+            Err(e) => {
+                log::error!("Network error sending request: {e}");
+                return None;
+            }
+        };
+        if !res.status().is_success() {
+            log::error!("Unexpected status {}", res.status());
+            return None;
+        }
+        let Ok(text) = res.text().await else {
+            log::error!("Network error getting body");
+            return None;
+        };
+        let Ok(beatmaps_response) = serde_json::from_str::<GetBeatmapsResponse>(&text) else {
+            log::error!("Unexpected response format: {text}");
+            return None;
+        };
+
+        let mut out: HashMap<u64, Beatmap> = HashMap::new();
+        for beatmap in beatmaps_response.beatmaps {
+            log::info!(
+                "Submitted found: {} - {}[{}]",
+                beatmap.beatmapset.artist,
+                beatmap.beatmapset.title,
+                beatmap.version
+            );
+            out.insert(beatmap.id, beatmap);
+        }
+        Some(out)
+    }
+
+    /// Looks up each of `set_ids` once, several in flight at once.
+    pub async fn lookup_beatmapsets(&self, set_ids: &[u64]) -> HashMap<u64, SetStatus> {
+        stream::iter(set_ids.iter().enumerate())
+            .map(|(index, set_id)| async move {
+                log::info!("[{}/{}] Checking set {set_id}.", index + 1, set_ids.len());
+                (*set_id, self.fetch_beatmapset(*set_id).await)
+            })
+            .buffer_unordered(self.max_concurrent_requests)
+            .collect()
+            .await
+    }
+
+    /// The set's difficulties, or whether it's missing. `Unknown` when the API couldn't give
+    /// a straight answer, the reason is logged.
+    pub async fn fetch_beatmapset(&self, set_id: u64) -> SetStatus {
+        let mut url = self.beatmapsets_url.clone();
+        url.path_segments_mut()
+            .expect("http url")
+            .push(&set_id.to_string());
+
+        let res = match self.send_authorized(|| self.client.get(url.clone())).await {
+            Ok(res) => res,
+            Err(e) => {
+                log::error!("Network error looking up set {set_id}: {e}");
+                return SetStatus::Unknown;
+            }
+        };
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return SetStatus::NotFound;
+        }
+        if !res.status().is_success() {
+            log::error!(
+                "Unexpected status looking up set {set_id}: {}",
+                res.status()
+            );
+            return SetStatus::Unknown;
+        }
+        let Ok(text) = res.text().await else {
+            log::error!("Network error getting body");
+            return SetStatus::Unknown;
+        };
+        match serde_json::from_str::<GetBeatmapsetResponse>(&text) {
+            Ok(set) => {
+                log::info!("Submitted set found: {} - {}", set.artist, set.title);
+                SetStatus::Submitted(set.into_beatmaps())
+            }
+            Err(e) => {
+                log::error!("Unexpected response format for set {set_id} ({e}): {text}");
+                SetStatus::Unknown
+            }
+        }
+    }
+
+    /// Sends the request built by `build` with JSON headers and the current token, retried
+    /// as usual. A 401 gets the token replaced and the request sent once more. Every attempt
+    /// waits for the rate limiter.
+    async fn send_authorized(
+        &self,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let build = &build;
+        let send = |authorization: String| {
+            send_paced_with_retry(&self.retry, &self.limiter, move || {
+                build()
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header(reqwest::header::ACCEPT, "application/json")
+                    .header(reqwest::header::AUTHORIZATION, authorization.clone())
+            })
+        };
+        let authorization = self.tokens.authorization().await;
+        let res = send(authorization.clone()).await?;
+        if res.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+        match self.tokens.reauthenticate(&authorization).await {
+            Some(renewed) => send(renewed).await,
+            None => Ok(res),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failed_chunks_are_unknown_and_retried() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            for (status, body) in [
+                ("500 Internal Server Error", ""),
+                ("200 OK", "not json"),
+                ("500 Internal Server Error", ""),
+                (
+                    "200 OK",
+                    r#"{"beatmaps":[{"beatmapset_id":10,"id":1,"status":"ranked","version":"x","checksum":null,"beatmapset":{"artist":"a","title":"t"}}]}"#,
+                ),
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let api = OsuApiClient::with_token("token".to_owned(), RetryPolicy::new(1))
+            .beatmaps_url(url)
+            .rate_limit_per_minute(60000)
+            .unknown_retry_passes(0);
+
+        // Neither a failed request nor a body that doesn't parse says anything about the IDs.
+        for _ in 0..2 {
+            let statuses = api.lookup_submission_statuses(&[1, 2], |_| {}).await;
+            assert_eq!(statuses.len(), 2);
+            assert!(statuses.values().all(SubmissionStatus::is_unknown));
+        }
+
+        let api = api.unknown_retry_passes(1);
+        let mut reported = Vec::new();
+        let statuses = api
+            .lookup_submission_statuses(&[1, 2], |chunk| {
+                reported.extend(
+                    chunk
+                        .iter()
+                        .filter(|(_, status)| !status.is_unknown())
+                        .map(|(id, _)| *id),
+                )
+            })
+            .await;
+        reported.sort_unstable();
+        assert_eq!(reported, [1, 2]);
+        assert!(matches!(statuses[&1], SubmissionStatus::Submitted(_)));
+        assert!(matches!(statuses[&2], SubmissionStatus::NotFound));
+        assert_eq!(api.requests_made(), 4);
+    }
+}
@@ -0,0 +1,1234 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    assets,
+    classify::FolderSelection,
+    song_folder::{FolderFile, SongFolder},
+};
+
+/// Formats that are already compressed, deflating them again only costs time.
+const STORED_EXTENSIONS: [&str; 8] = ["mp3", "ogg", "jpg", "jpeg", "png", "mp4", "avi", "flv"];
+
+const TAR_BLOCK_SIZE: u64 = 512;
+/// Longer entry names need a GNU long name entry of their own.
+const TAR_MAX_NAME_LEN: u64 = 100;
+/// Fixed parts of a zip's local file header and central directory entry.
+const ZIP_ENTRY_OVERHEAD: u64 = 30 + 46;
+const ZIP_END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 22;
+
+/// Where archived folders go.
+#[derive(Debug, Clone)]
+pub enum Output {
+    /// Everything in one tar.
+    Tar(PathBuf),
+    /// One importable `.osz` per song folder in this directory.
+    OszDir(PathBuf),
+    /// The song folders themselves in this directory, shaped like a Songs folder.
+    Dir(PathBuf),
+}
+
+/// How a directory output gets its files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MirrorStrategy {
+    #[default]
+    Copy,
+    /// Hard links to the originals, which takes no space but needs the same filesystem and
+    /// shares edits made on either side.
+    Hardlink,
+    /// Copy-on-write clones where the filesystem supports them (Btrfs, XFS, APFS, ReFS),
+    /// copies where it doesn't.
+    Reflink,
+}
+
+/// How a tar output is compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TarFormat {
+    #[default]
+    Plain,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl TarFormat {
+    /// The format `path`'s extension names, plain tar for anything unrecognised.
+    pub fn from_path(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            TarFormat::Gzip
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            TarFormat::Zstd
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            TarFormat::Xz
+        } else {
+            TarFormat::Plain
+        }
+    }
+
+    /// The compression levels the encoder accepts, `None` for plain tar.
+    pub fn levels(self) -> Option<RangeInclusive<i32>> {
+        match self {
+            TarFormat::Plain => None,
+            TarFormat::Gzip | TarFormat::Xz => Some(0..=9),
+            TarFormat::Zstd => Some(1..=22),
+        }
+    }
+
+    fn default_level(self) -> i32 {
+        match self {
+            TarFormat::Plain => 0,
+            TarFormat::Gzip | TarFormat::Xz => 6,
+            TarFormat::Zstd => 3,
+        }
+    }
+}
+
+/// Settings for compressing tar output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TarCompression {
+    pub format: TarFormat,
+    /// The format's own default if `None`.
+    pub level: Option<i32>,
+    /// Threads zstd compresses on, `0` compresses on the writing thread.
+    pub workers: u32,
+}
+
+impl Default for TarCompression {
+    fn default() -> Self {
+        Self {
+            format: TarFormat::Plain,
+            level: None,
+            workers: 0,
+        }
+    }
+}
+
+impl TarCompression {
+    fn level(&self) -> i32 {
+        self.level.unwrap_or(self.format.default_level())
+    }
+}
+
+/// One of the tars a size-limited output is split into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Volume {
+    pub path: PathBuf,
+    /// The slice of the folders to archive that goes in this volume.
+    pub folders: Range<usize>,
+}
+
+/// Which song folder went into which volume, written next to them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeIndex {
+    pub volumes: Vec<VolumeIndexEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeIndexEntry {
+    /// The volume's file name.
+    pub file: String,
+    /// The song folder names inside it.
+    pub folders: Vec<String>,
+}
+
+/// What an interrupted run had already written.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub archived: HashSet<PathBuf>,
+    /// Where the tar stream ended after the last folder archived.
+    pub tar_offset: Option<u64>,
+}
+
+impl Progress {
+    pub fn is_empty(&self) -> bool {
+        self.archived.is_empty()
+    }
+}
+
+/// What [`Archiver::write`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteSummary {
+    /// Folders written, not counting the ones an interrupted run already wrote.
+    pub folders: usize,
+    /// Files stored as links to an identical file written before them.
+    pub deduplicated_files: usize,
+    /// The contents of those files, which weren't written again.
+    pub bytes_saved: u64,
+    /// Files a directory output already had as they are, from an earlier run.
+    pub unchanged_files: usize,
+}
+
+/// Packs selected song folders into the chosen output.
+pub struct Archiver {
+    output: Output,
+    only_selected_difficulties: bool,
+    deduplicate: bool,
+    compression: TarCompression,
+    max_volume_size: Option<u64>,
+    mirror_strategy: MirrorStrategy,
+}
+
+impl Archiver {
+    pub fn new(output: Output) -> Self {
+        Self {
+            output,
+            only_selected_difficulties: false,
+            deduplicate: false,
+            compression: TarCompression::default(),
+            max_volume_size: None,
+            mirror_strategy: MirrorStrategy::default(),
+        }
+    }
+
+    /// Only pack the selected .osu files and the assets they reference, not whole folders.
+    pub fn only_selected_difficulties(mut self, only: bool) -> Self {
+        self.only_selected_difficulties = only;
+        self
+    }
+
+    /// Store files identical to one already written as hard links to it, in outputs that can:
+    /// link entries in a tar, filesystem hard links in a directory. `.osz` files can't, each is
+    /// imported on its own.
+    pub fn deduplicate(mut self, deduplicate: bool) -> Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+
+    /// How to compress tar output. `.osz` files are always deflated.
+    pub fn compression(mut self, compression: TarCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Split tar output into volumes of at most this many bytes, `songs.001.tar`,
+    /// `songs.002.tar` and so on, never splitting a song folder. A folder bigger than that
+    /// gets a volume of its own.
+    pub fn max_volume_size(mut self, max_volume_size: Option<u64>) -> Self {
+        self.max_volume_size = max_volume_size;
+        self
+    }
+
+    /// How a directory output gets its files.
+    pub fn mirror_strategy(mut self, mirror_strategy: MirrorStrategy) -> Self {
+        self.mirror_strategy = mirror_strategy;
+        self
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Whether projected sizes are exact rather than an upper bound.
+    pub fn exact_sizes(&self) -> bool {
+        matches!(self.output, Output::Tar(_)) && self.compression.format == TarFormat::Plain
+    }
+
+    /// The files to write for each selected folder. The order is fixed so a resumed run skips
+    /// exactly the folders already written.
+    pub fn folders_to_archive(
+        &self,
+        selection: FolderSelection,
+        song_folders: &HashMap<PathBuf, SongFolder>,
+    ) -> Vec<FolderToArchive> {
+        let mut folders: Vec<FolderToArchive> = selection
+            .into_iter()
+            .map(|(folder, difficulties)| {
+                let local = &song_folders[&folder];
+                let folder_files = local.files();
+                let files = if self.only_selected_difficulties {
+                    let difficulties: Vec<PathBuf> = difficulties.into_iter().collect();
+                    assets::select_files(&folder, &folder_files, &difficulties)
+                } else {
+                    folder_files
+                };
+                let metadata = &local.difficulties[0].metadata;
+                FolderToArchive {
+                    set_id: local.set_id(),
+                    artist: metadata.artist.clone(),
+                    title: metadata.title.clone(),
+                    folder,
+                    files,
+                }
+            })
+            .collect();
+        folders.sort_by(|a, b| a.folder.cmp(&b.folder));
+        folders
+    }
+
+    /// Whether the output left by an interrupted run is still there to continue. A compressed
+    /// tar can't be cut back to where it stopped, so it never can.
+    pub fn can_resume(&self, folders: &[FolderToArchive], progress: &Progress) -> bool {
+        match &self.output {
+            Output::Tar(_) if self.compression.format != TarFormat::Plain => false,
+            Output::Tar(_) => {
+                let Ok(volumes) = self.volumes(folders) else {
+                    return false;
+                };
+                let resumed = last_touched_volume(&volumes, folders, progress);
+                progress.tar_offset.is_some_and(|offset| {
+                    resumed.is_some_and(|i| tar_resumable(&volumes[i].path, offset))
+                })
+            }
+            Output::OszDir(dir) | Output::Dir(dir) => dir.is_dir(),
+        }
+    }
+
+    /// Which folders go in which tar. A single volume at the output path unless a
+    /// [maximum size](Self::max_volume_size) is set, and none for other outputs.
+    ///
+    /// Volumes are filled as if nothing were deduplicated, so they stay under the maximum
+    /// whatever links end up in them.
+    pub fn volumes(&self, folders: &[FolderToArchive]) -> io::Result<Vec<Volume>> {
+        let Output::Tar(path) = &self.output else {
+            return Ok(Vec::new());
+        };
+        let Some(max_volume_size) = self.max_volume_size else {
+            return Ok(vec![Volume {
+                path: path.clone(),
+                folders: 0..folders.len(),
+            }]);
+        };
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        let mut size = 2 * TAR_BLOCK_SIZE;
+        for (i, folder) in folders.iter().enumerate() {
+            let folder_size = self.projected_size(folder, None)?;
+            if i > start && size + folder_size > max_volume_size {
+                ranges.push(start..i);
+                start = i;
+                size = 2 * TAR_BLOCK_SIZE;
+            }
+            if i == start && size + folder_size > max_volume_size {
+                log::warn!(
+                    "{:?} alone is over the maximum volume size, it gets a volume of its own",
+                    folder.folder
+                );
+            }
+            size += folder_size;
+        }
+        ranges.push(start..folders.len());
+        Ok(ranges
+            .into_iter()
+            .enumerate()
+            .map(|(i, folders)| Volume {
+                path: volume_path(path, i + 1),
+                folders,
+            })
+            .collect())
+    }
+
+    /// How many bytes each folder would add to the output, and the size of the whole output,
+    /// without writing anything. Plain tar sizes are exact, which takes hashing every file when
+    /// deduplicating. Compressed tars are sized before compression, `.osz` sizes assume
+    /// nothing deflates and directory sizes count every file as copied, so those are an upper
+    /// bound.
+    pub fn projected_sizes(&self, folders: &[FolderToArchive]) -> io::Result<(Vec<u64>, u64)> {
+        let Output::Tar(_) = self.output else {
+            let sizes = folders
+                .iter()
+                .map(|folder| self.projected_size(folder, None))
+                .collect::<io::Result<Vec<u64>>>()?;
+            let total = sizes.iter().sum();
+            return Ok((sizes, total));
+        };
+        let mut sizes = Vec::with_capacity(folders.len());
+        let mut total = 0;
+        for volume in self.volumes(folders)? {
+            // Links only ever point within a volume.
+            let mut index = ContentIndex::default();
+            let start = sizes.len();
+            for folder in &folders[volume.folders] {
+                sizes.push(self.projected_size(folder, Some(&mut index))?);
+            }
+            total += tar_size(&sizes[start..]);
+        }
+        Ok((sizes, total))
+    }
+
+    /// Deduplicates against `index` if given and deduplicating.
+    fn projected_size(
+        &self,
+        folder: &FolderToArchive,
+        mut index: Option<&mut ContentIndex>,
+    ) -> io::Result<u64> {
+        let mut size = match self.output {
+            Output::Tar(_) => 0,
+            Output::OszDir(_) => ZIP_END_OF_CENTRAL_DIRECTORY_SIZE,
+            Output::Dir(_) => 0,
+        };
+        for (file, relative) in folder.relative_files() {
+            let len = std::fs::metadata(file)?.len();
+            size += match self.output {
+                Output::Tar(_) => {
+                    let name = folder.folder_name().join(relative);
+                    let linked = match index.as_deref_mut() {
+                        Some(index) if self.deduplicate => index.existing(file, &name)?,
+                        _ => None,
+                    };
+                    let contents = match linked {
+                        Some(target) => tar_long_name_size(&target),
+                        None => tar_padded(len),
+                    };
+                    tar_long_name_size(&name) + TAR_BLOCK_SIZE + contents
+                }
+                Output::OszDir(_) => {
+                    ZIP_ENTRY_OVERHEAD + 2 * relative.as_os_str().len() as u64 + len
+                }
+                Output::Dir(_) => len,
+            };
+        }
+        Ok(size)
+    }
+
+    /// Writes `folders`, skipping the ones in `progress`. `on_archived` is told about each
+    /// folder once it's fully written, along with the position in its tar after it for plain
+    /// tar output. Split output also gets a [`VolumeIndex`] next to the volumes.
+    pub fn write(
+        &self,
+        folders: &[FolderToArchive],
+        progress: &Progress,
+        mut on_archived: impl FnMut(&Path, Option<u64>),
+    ) -> io::Result<WriteSummary> {
+        match &self.output {
+            Output::Tar(path) => {
+                let volumes = self.volumes(folders)?;
+                // Volumes before the one the interrupted run stopped in are complete.
+                let resumed = last_touched_volume(&volumes, folders, progress);
+                let mut summary = WriteSummary::default();
+                for (i, volume) in volumes.iter().enumerate() {
+                    if resumed.is_some_and(|resumed| i < resumed) {
+                        continue;
+                    }
+                    let resume_offset = progress.tar_offset.filter(|_| resumed == Some(i));
+                    let volume_summary = write_tar(
+                        &volume.path,
+                        &folders[volume.folders.clone()],
+                        &progress.archived,
+                        resume_offset,
+                        self.deduplicate,
+                        self.compression,
+                        |folder, offset| {
+                            summary.folders += 1;
+                            on_archived(folder, offset)
+                        },
+                    )?;
+                    summary.deduplicated_files += volume_summary.deduplicated_files;
+                    summary.bytes_saved += volume_summary.bytes_saved;
+                }
+                if self.max_volume_size.is_some() {
+                    write_volume_index(&volume_index_path(path), &volumes, folders)?;
+                }
+                Ok(summary)
+            }
+            Output::OszDir(dir) => {
+                let written = write_osz_dir(dir, folders, &progress.archived, |folder| {
+                    on_archived(folder, None)
+                })?;
+                Ok(WriteSummary {
+                    folders: written.len(),
+                    ..Default::default()
+                })
+            }
+            Output::Dir(dir) => write_dir(
+                dir,
+                folders,
+                &progress.archived,
+                self.mirror_strategy,
+                self.deduplicate,
+                |folder| on_archived(folder, None),
+            ),
+        }
+    }
+}
+
+/// A song folder to write out, and which of its files to include.
+pub struct FolderToArchive {
+    pub folder: PathBuf,
+    pub files: Vec<FolderFile>,
+    pub set_id: Option<u64>,
+    pub artist: String,
+    pub title: String,
+}
+
+impl FolderToArchive {
+    fn folder_name(&self) -> &Path {
+        Path::new(self.folder.file_name().expect("song folders have names"))
+    }
+
+    fn relative_files(&self) -> impl Iterator<Item = (&Path, &Path)> {
+        self.files
+            .iter()
+            .map(|file| (file.source.as_path(), file.name.as_path()))
+    }
+
+    /// `<set id or "local"> <Artist> - <Title>.osz`, the same shape osu! uses for downloads.
+    pub fn osz_file_name(&self) -> String {
+        let set_id = self
+            .set_id
+            .map_or_else(|| "local".to_owned(), |id| id.to_string());
+        sanitize_file_name(&format!(
+            "{set_id} {} - {}.osz",
+            self.artist.trim(),
+            self.title.trim()
+        ))
+    }
+}
+
+/// Files already written, by content, so identical ones after them can link back.
+#[derive(Default)]
+struct ContentIndex {
+    /// (length, MD5) -> (source file, name it was written as).
+    written: HashMap<(u64, [u8; 16]), (PathBuf, PathBuf)>,
+}
+
+impl ContentIndex {
+    /// The name an identical file was written as, or `None` after remembering `file` as
+    /// written under `name`. A matching hash is confirmed byte for byte before linking.
+    fn existing(&mut self, file: &Path, name: &Path) -> io::Result<Option<PathBuf>> {
+        let len = std::fs::metadata(file)?.len();
+        if len == 0 {
+            // Nothing to save.
+            return Ok(None);
+        }
+        let mut context = md5::Context::new();
+        io::copy(&mut File::open(file)?, &mut context)?;
+        let key = (len, context.compute().0);
+        if let Some((source, written_as)) = self.written.get(&key) {
+            if same_contents(source, file)? {
+                return Ok(Some(written_as.clone()));
+            }
+            return Ok(None);
+        }
+        self.written.insert(key, (file.to_owned(), name.to_owned()));
+        Ok(None)
+    }
+}
+
+/// Whether two files of the same length hold the same bytes.
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut a = File::open(a)?;
+    let mut b = File::open(b)?;
+    let mut a_buf = vec![0; 64 * 1024];
+    let mut b_buf = vec![0; 64 * 1024];
+    loop {
+        let n = a.read(&mut a_buf)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut b_buf[..n])?;
+        if a_buf[..n] != b_buf[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Writes every folder into one tar, under its original folder name, compressed as asked.
+/// With `deduplicate`, files identical to one written before are stored as hard link entries
+/// to it.
+///
+/// Folders in `skip` were written by an interrupted run whose plain tar stream ended at
+/// `resume_offset`; the file is cut back to there and the rest appended. `on_archived` is told
+/// the stream position after each folder, which a compressed tar has none of.
+fn write_tar(
+    path: &Path,
+    folders: &[FolderToArchive],
+    skip: &HashSet<PathBuf>,
+    resume_offset: Option<u64>,
+    deduplicate: bool,
+    compression: TarCompression,
+    mut on_archived: impl FnMut(&Path, Option<u64>),
+) -> io::Result<WriteSummary> {
+    let level = compression.level();
+    match compression.format {
+        TarFormat::Plain => {
+            let file = match resume_offset {
+                Some(offset) => {
+                    let mut file = OpenOptions::new().write(true).open(path)?;
+                    // Anything past the last checkpoint is a partly written folder or the end
+                    // marker.
+                    file.set_len(offset)?;
+                    file.seek(SeekFrom::Start(offset))?;
+                    file
+                }
+                None => File::create(path)?,
+            };
+            let mut tarrer = tar::Builder::new(file);
+            let summary = append_folders(
+                &mut tarrer,
+                folders,
+                skip,
+                deduplicate,
+                |file| file.stream_position().map(Some),
+                &mut on_archived,
+            )?;
+            tarrer.finish()?;
+            Ok(summary)
+        }
+        TarFormat::Gzip => {
+            let encoder = flate2::write::GzEncoder::new(
+                BufWriter::new(File::create(path)?),
+                flate2::Compression::new(level as u32),
+            );
+            let mut tarrer = tar::Builder::new(encoder);
+            let summary = append_folders(
+                &mut tarrer,
+                folders,
+                skip,
+                deduplicate,
+                |_| Ok(None),
+                &mut on_archived,
+            )?;
+            tarrer.into_inner()?.finish()?.flush()?;
+            Ok(summary)
+        }
+        TarFormat::Zstd => {
+            let mut encoder = zstd::Encoder::new(BufWriter::new(File::create(path)?), level)?;
+            encoder.multithread(compression.workers)?;
+            let mut tarrer = tar::Builder::new(encoder);
+            let summary = append_folders(
+                &mut tarrer,
+                folders,
+                skip,
+                deduplicate,
+                |_| Ok(None),
+                &mut on_archived,
+            )?;
+            tarrer.into_inner()?.finish()?.flush()?;
+            Ok(summary)
+        }
+        TarFormat::Xz => {
+            let encoder =
+                xz2::write::XzEncoder::new(BufWriter::new(File::create(path)?), level as u32);
+            let mut tarrer = tar::Builder::new(encoder);
+            let summary = append_folders(
+                &mut tarrer,
+                folders,
+                skip,
+                deduplicate,
+                |_| Ok(None),
+                &mut on_archived,
+            )?;
+            tarrer.into_inner()?.finish()?.flush()?;
+            Ok(summary)
+        }
+    }
+}
+
+/// Appends the folders not in `skip` to `tarrer`, telling `on_archived` about each along with
+/// the `position` of the stream after it.
+fn append_folders<W: Write>(
+    tarrer: &mut tar::Builder<W>,
+    folders: &[FolderToArchive],
+    skip: &HashSet<PathBuf>,
+    deduplicate: bool,
+    position: impl Fn(&mut W) -> io::Result<Option<u64>>,
+    on_archived: &mut impl FnMut(&Path, Option<u64>),
+) -> io::Result<WriteSummary> {
+    let mut index = ContentIndex::default();
+    let mut summary = WriteSummary::default();
+    for folder in folders {
+        let skipped = skip.contains(&folder.folder);
+        if skipped && !deduplicate {
+            continue;
+        }
+        for (file, relative) in folder.relative_files() {
+            let name = folder.folder_name().join(relative);
+            // Skipped folders are indexed too, so links resolve as they did before.
+            let linked = if deduplicate {
+                index.existing(file, &name)?
+            } else {
+                None
+            };
+            if skipped {
+                continue;
+            }
+            match linked {
+                Some(target) => {
+                    let metadata = std::fs::metadata(file)?;
+                    let mut header = tar::Header::new_gnu();
+                    header.set_metadata(&metadata);
+                    header.set_entry_type(tar::EntryType::Link);
+                    header.set_size(0);
+                    tarrer.append_link(&mut header, &name, &target)?;
+                    summary.deduplicated_files += 1;
+                    summary.bytes_saved += metadata.len();
+                }
+                None => tarrer.append_path_with_name(file, &name)?,
+            }
+        }
+        if !skipped {
+            on_archived(&folder.folder, position(tarrer.get_mut())?);
+        }
+    }
+    Ok(summary)
+}
+
+/// The size of a tar holding folders of these projected sizes.
+pub fn tar_size(folder_sizes: &[u64]) -> u64 {
+    // Plus the end of archive marker.
+    folder_sizes.iter().sum::<u64>() + 2 * TAR_BLOCK_SIZE
+}
+
+/// The volume holding the last folder an interrupted run archived, if it archived any.
+fn last_touched_volume(
+    volumes: &[Volume],
+    folders: &[FolderToArchive],
+    progress: &Progress,
+) -> Option<usize> {
+    volumes.iter().rposition(|volume| {
+        folders[volume.folders.clone()]
+            .iter()
+            .any(|folder| progress.archived.contains(&folder.folder))
+    })
+}
+
+/// The name `path` is split off from at the first `.tar` (or `.tgz`, ...) with the volume
+/// number before it, e.g. `songs.tar.zst` -> `songs.002.tar.zst`.
+pub fn volume_path(path: &Path, number: usize) -> PathBuf {
+    let (stem, extension) = split_archive_extension(path);
+    path.with_file_name(format!("{stem}.{number:03}{extension}"))
+}
+
+/// `songs.tar.zst` -> `songs.index.json`.
+pub fn volume_index_path(path: &Path) -> PathBuf {
+    let (stem, _) = split_archive_extension(path);
+    path.with_file_name(format!("{stem}.index.json"))
+}
+
+fn split_archive_extension(path: &Path) -> (String, String) {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let lowercase = name.to_lowercase();
+    let extension_len = [
+        ".tar.gz", ".tar.zst", ".tar.xz", ".tgz", ".tzst", ".txz", ".tar",
+    ]
+    .into_iter()
+    .find(|extension| lowercase.ends_with(extension))
+    .map_or(0, str::len);
+    let (stem, extension) = name.split_at(name.len() - extension_len);
+    (stem.to_owned(), extension.to_owned())
+}
+
+fn write_volume_index(
+    path: &Path,
+    volumes: &[Volume],
+    folders: &[FolderToArchive],
+) -> io::Result<()> {
+    let index = VolumeIndex {
+        volumes: volumes
+            .iter()
+            .map(|volume| VolumeIndexEntry {
+                file: volume
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                folders: folders[volume.folders.clone()]
+                    .iter()
+                    .map(|folder| folder.folder_name().to_string_lossy().into_owned())
+                    .collect(),
+            })
+            .collect(),
+    };
+    serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &index)?;
+    Ok(())
+}
+
+/// The GNU long name entry `name` needs in front of its own, if any. Link targets get one the
+/// same way.
+fn tar_long_name_size(name: &Path) -> u64 {
+    let len = name.as_os_str().len() as u64;
+    if len > TAR_MAX_NAME_LEN {
+        TAR_BLOCK_SIZE + tar_padded(len + 1)
+    } else {
+        0
+    }
+}
+
+/// `len` rounded up to whole tar blocks.
+fn tar_padded(len: u64) -> u64 {
+    len.div_ceil(TAR_BLOCK_SIZE) * TAR_BLOCK_SIZE
+}
+
+/// Whether a tar left by an interrupted run can be continued from `offset`.
+fn tar_resumable(path: &Path, offset: u64) -> bool {
+    std::fs::metadata(path).is_ok_and(|meta| meta.len() >= offset)
+}
+
+/// Mirrors every folder into `dir` under its original folder name. Files already there with
+/// the same contents are left alone, so a repeated run only writes what changed. With
+/// `deduplicate`, files identical to one mirrored before are hard linked to it.
+fn write_dir(
+    dir: &Path,
+    folders: &[FolderToArchive],
+    skip: &HashSet<PathBuf>,
+    strategy: MirrorStrategy,
+    deduplicate: bool,
+    mut on_archived: impl FnMut(&Path),
+) -> io::Result<WriteSummary> {
+    std::fs::create_dir_all(dir)?;
+    // Hard links to the originals take no space to begin with.
+    let deduplicate = deduplicate && strategy != MirrorStrategy::Hardlink;
+    let mut index = ContentIndex::default();
+    let mut summary = WriteSummary::default();
+    let mut warned_reflink = false;
+    for folder in folders.iter().filter(|f| !skip.contains(&f.folder)) {
+        for (file, relative) in folder.relative_files() {
+            let target = dir.join(folder.folder_name()).join(relative);
+            let unchanged = std::fs::metadata(&target).is_ok_and(|target_meta| {
+                std::fs::metadata(file).is_ok_and(|meta| meta.len() == target_meta.len())
+            }) && same_contents(file, &target)?;
+            let linked = if deduplicate {
+                index.existing(file, &target)?
+            } else {
+                None
+            };
+            if unchanged {
+                summary.unchanged_files += 1;
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Never write through an old link, it would change whatever else it points at.
+            match std::fs::remove_file(&target) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            match (linked, strategy) {
+                (Some(existing), _) => {
+                    std::fs::hard_link(existing, &target)?;
+                    summary.deduplicated_files += 1;
+                    summary.bytes_saved += std::fs::metadata(file)?.len();
+                }
+                (None, MirrorStrategy::Copy) => {
+                    std::fs::copy(file, &target)?;
+                }
+                (None, MirrorStrategy::Hardlink) => std::fs::hard_link(file, &target)?,
+                (None, MirrorStrategy::Reflink) => {
+                    if reflink_copy::reflink_or_copy(file, &target)?.is_some() && !warned_reflink {
+                        log::warn!("{dir:?} can't clone files, copying them instead");
+                        warned_reflink = true;
+                    }
+                }
+            }
+        }
+        on_archived(&folder.folder);
+        summary.folders += 1;
+    }
+    Ok(summary)
+}
+
+/// Writes one `.osz` per folder into `dir`, returning the paths written.
+///
+/// Folders in `skip` were written by an interrupted run. They still claim their names so the
+/// ones written now come out the same as in an uninterrupted run.
+fn write_osz_dir(
+    dir: &Path,
+    folders: &[FolderToArchive],
+    skip: &HashSet<PathBuf>,
+    mut on_archived: impl FnMut(&Path),
+) -> io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut used_names = HashSet::new();
+    let mut written = Vec::new();
+    for folder in folders {
+        let file_name = unique_name(folder.osz_file_name(), &mut used_names);
+        if skip.contains(&folder.folder) {
+            continue;
+        }
+        let path = dir.join(file_name);
+        write_osz(&path, folder)?;
+        on_archived(&folder.folder);
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn write_osz(path: &Path, folder: &FolderToArchive) -> io::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    for (file, relative) in folder.relative_files() {
+        let stored = relative
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| STORED_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        let options = SimpleFileOptions::default()
+            .compression_method(if stored {
+                CompressionMethod::Stored
+            } else {
+                CompressionMethod::Deflated
+            })
+            .large_file(std::fs::metadata(file)?.len() >= u32::MAX as u64);
+        // Zip paths always use forward slashes.
+        let name = relative.to_string_lossy().replace('\\', "/");
+        zip.start_file(name, options)?;
+        io::copy(&mut File::open(file)?, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn unique_name(file_name: String, used_names: &mut HashSet<String>) -> String {
+    let mut candidate = file_name.clone();
+    let mut n = 2;
+    while !used_names.insert(candidate.to_lowercase()) {
+        let (stem, extension) = file_name.rsplit_once('.').unwrap_or((&file_name, ""));
+        candidate = format!("{stem} ({n}).{extension}");
+        n += 1;
+    }
+    candidate
+}
+
+/// Strips characters Windows won't allow in file names.
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .filter(|c| !matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'))
+        .filter(|c| !c.is_control())
+        .collect();
+    sanitized.trim_end_matches(['.', ' ']).to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A directory under the system temp dir, removed when dropped so a failing assert
+    /// doesn't leave it behind.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Song folders `names` in a fresh temp dir for `test`, each holding `files`. Files without
+    /// contents get their folder's name, so they differ between folders.
+    fn song_folders(
+        test: &str,
+        names: &[&str],
+        files: &[(&str, Option<&[u8]>)],
+    ) -> (TempDir, Vec<FolderToArchive>) {
+        let dir = TempDir(
+            std::env::temp_dir().join(format!("archive-{test}-test-{}", std::process::id())),
+        );
+        let _ = std::fs::remove_dir_all(dir.path());
+        let folders = names
+            .iter()
+            .map(|name| {
+                let folder = dir.path().join(name);
+                let folder_name = folder.file_name().unwrap().as_encoded_bytes();
+                for (file, contents) in files {
+                    let path = folder.join(file);
+                    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    std::fs::write(path, contents.unwrap_or(folder_name)).unwrap();
+                }
+                FolderToArchive {
+                    files: files
+                        .iter()
+                        .map(|(file, _)| FolderFile {
+                            source: folder.join(file),
+                            name: PathBuf::from(file),
+                        })
+                        .collect(),
+                    folder,
+                    set_id: None,
+                    artist: "a".to_owned(),
+                    title: "b".to_owned(),
+                }
+            })
+            .collect();
+        (dir, folders)
+    }
+
+    #[test]
+    fn test_osz_file_name() {
+        let folder = FolderToArchive {
+            folder: PathBuf::from("Songs/123 a - b"),
+            files: Vec::new(),
+            set_id: None,
+            artist: "AC/DC".to_owned(),
+            title: "What?: ".to_owned(),
+        };
+        assert_eq!(folder.osz_file_name(), "local ACDC - What.osz");
+        let folder = FolderToArchive {
+            set_id: Some(292599),
+            artist: "5StepSoundTeam".to_owned(),
+            title: "NeverSayDie".to_owned(),
+            ..folder
+        };
+        assert_eq!(
+            folder.osz_file_name(),
+            "292599 5StepSoundTeam - NeverSayDie.osz"
+        );
+
+        let mut used = HashSet::new();
+        assert_eq!(unique_name("a.osz".to_owned(), &mut used), "a.osz");
+        assert_eq!(unique_name("A.osz".to_owned(), &mut used), "A (2).osz");
+    }
+
+    #[test]
+    fn test_projected_tar_size() {
+        let long_name = format!("sb/{}.png", "x".repeat(120));
+        let (dir, folders) = song_folders(
+            "size",
+            &["123 a - b"],
+            &[
+                ("a.osu", Some(b"osu file format v14")),
+                ("audio.mp3", Some(&[0; 1500])),
+                (&long_name, Some(b"")),
+            ],
+        );
+
+        let tar_path = dir.path().join("out.tar");
+        let archiver = Archiver::new(Output::Tar(tar_path.clone()));
+        let (sizes, total) = archiver.projected_sizes(&folders).unwrap();
+        archiver
+            .write(&folders, &Progress::default(), |_, _| {})
+            .unwrap();
+        assert_eq!(total, std::fs::metadata(&tar_path).unwrap().len());
+        assert_eq!(sizes, vec![total - 2 * TAR_BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn test_deduplicated_tar() {
+        let long_name = format!("{}.png", "x".repeat(120));
+        let (dir, folders) = song_folders(
+            "dedup",
+            &["1 a - b", "2 a - b (fork)"],
+            &[
+                ("a.osu", None),
+                ("audio.mp3", Some(&[7; 1500])),
+                (&long_name, Some(&[1; 10])),
+            ],
+        );
+
+        let tar_path = dir.path().join("out.tar");
+        let archiver = Archiver::new(Output::Tar(tar_path.clone())).deduplicate(true);
+        let (_, total) = archiver.projected_sizes(&folders).unwrap();
+        let summary = archiver
+            .write(&folders, &Progress::default(), |_, _| {})
+            .unwrap();
+        assert_eq!(
+            summary,
+            WriteSummary {
+                folders: 2,
+                deduplicated_files: 2,
+                bytes_saved: 1510,
+                unchanged_files: 0,
+            }
+        );
+        assert_eq!(total, std::fs::metadata(&tar_path).unwrap().len());
+
+        let mut archive = tar::Archive::new(File::open(&tar_path).unwrap());
+        let links: Vec<(PathBuf, PathBuf)> = archive
+            .entries()
+            .unwrap()
+            .map(Result::unwrap)
+            .filter(|entry| entry.header().entry_type() == tar::EntryType::Link)
+            .map(|entry| {
+                (
+                    entry.path().unwrap().into_owned(),
+                    entry.link_name().unwrap().unwrap().into_owned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (
+                    Path::new("2 a - b (fork)/audio.mp3").to_owned(),
+                    Path::new("1 a - b/audio.mp3").to_owned()
+                ),
+                (
+                    Path::new("2 a - b (fork)").join(&long_name),
+                    Path::new("1 a - b").join(&long_name)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compressed_tar() {
+        assert_eq!(
+            TarFormat::from_path(Path::new("a.TAR.ZST")),
+            TarFormat::Zstd
+        );
+        assert_eq!(TarFormat::from_path(Path::new("a.tgz")), TarFormat::Gzip);
+        assert_eq!(TarFormat::from_path(Path::new("a.tar")), TarFormat::Plain);
+
+        let contents = "osu file format v14\n".repeat(1000);
+        let (dir, folders) = song_folders(
+            "compress",
+            &["123 a - b"],
+            &[("a.osu", Some(contents.as_bytes()))],
+        );
+
+        for format in [TarFormat::Gzip, TarFormat::Zstd, TarFormat::Xz] {
+            let tar_path = dir.path().join("out");
+            let archiver =
+                Archiver::new(Output::Tar(tar_path.clone())).compression(TarCompression {
+                    format,
+                    level: None,
+                    workers: 2,
+                });
+            let mut offsets = Vec::new();
+            archiver
+                .write(&folders, &Progress::default(), |_, offset| {
+                    offsets.push(offset)
+                })
+                .unwrap();
+            assert_eq!(offsets, vec![None]);
+            assert!(!archiver.can_resume(
+                &folders,
+                &Progress {
+                    archived: HashSet::from([folders[0].folder.clone()]),
+                    tar_offset: Some(0),
+                }
+            ));
+
+            let file = File::open(&tar_path).unwrap();
+            assert!(file.metadata().unwrap().len() < contents.len() as u64 / 10);
+            let decoder: Box<dyn Read> = match format {
+                TarFormat::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+                TarFormat::Zstd => Box::new(zstd::Decoder::new(file).unwrap()),
+                TarFormat::Xz => Box::new(xz2::read::XzDecoder::new(file)),
+                TarFormat::Plain => unreachable!(),
+            };
+            let mut archive = tar::Archive::new(decoder);
+            let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+            assert_eq!(&*entry.path().unwrap(), Path::new("123 a - b/a.osu"));
+            let mut unpacked = String::new();
+            entry.read_to_string(&mut unpacked).unwrap();
+            assert_eq!(unpacked, contents, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_volumes() {
+        assert_eq!(
+            volume_path(Path::new("out/Songs.TAR.zst"), 2),
+            Path::new("out/Songs.002.TAR.zst")
+        );
+        assert_eq!(volume_path(Path::new("songs"), 1), Path::new("songs.001"));
+        assert_eq!(
+            volume_index_path(Path::new("out/songs.tar")),
+            Path::new("out/songs.index.json")
+        );
+
+        let (dir, folders) = song_folders(
+            "volume",
+            &["1 a", "2 b", "3 c"],
+            &[("audio.mp3", Some(&[0; 1000]))],
+        );
+        let dir = dir.path();
+
+        // Each folder is a header and two blocks, so two fit with the end marker.
+        let tar_path = dir.join("songs.tar");
+        let archiver = Archiver::new(Output::Tar(tar_path.clone()))
+            .deduplicate(true)
+            .max_volume_size(Some(8 * TAR_BLOCK_SIZE));
+        let volumes = archiver.volumes(&folders).unwrap();
+        assert_eq!(
+            volumes,
+            vec![
+                Volume {
+                    path: dir.join("songs.001.tar"),
+                    folders: 0..2,
+                },
+                Volume {
+                    path: dir.join("songs.002.tar"),
+                    folders: 2..3,
+                },
+            ]
+        );
+        let (_, total) = archiver.projected_sizes(&folders).unwrap();
+        let summary = archiver
+            .write(&folders, &Progress::default(), |_, _| {})
+            .unwrap();
+        // The second folder's audio links back to the first's, the third's is in another volume.
+        assert_eq!(summary.folders, 3);
+        assert_eq!(summary.deduplicated_files, 1);
+        let written: u64 = volumes
+            .iter()
+            .map(|volume| std::fs::metadata(&volume.path).unwrap().len())
+            .sum();
+        assert_eq!(written, total);
+
+        let index: VolumeIndex =
+            serde_json::from_reader(File::open(volume_index_path(&tar_path)).unwrap()).unwrap();
+        assert_eq!(index.volumes[0].file, "songs.001.tar");
+        assert_eq!(index.volumes[0].folders, vec!["1 a", "2 b"]);
+        assert_eq!(index.volumes[1].folders, vec!["3 c"]);
+    }
+
+    #[test]
+    fn test_mirror_dir() {
+        let (dir, folders) = song_folders(
+            "mirror",
+            &["Songs/1 a - b", "Songs/2 a - b (fork)"],
+            &[("a.osu", None), ("sb/bg.png", Some(&[7; 1500]))],
+        );
+
+        let out = dir.path().join("out");
+        let archiver = Archiver::new(Output::Dir(out.clone())).deduplicate(true);
+        let summary = archiver
+            .write(&folders, &Progress::default(), |_, _| {})
+            .unwrap();
+        assert_eq!(
+            summary,
+            WriteSummary {
+                folders: 2,
+                deduplicated_files: 1,
+                bytes_saved: 1500,
+                unchanged_files: 0,
+            }
+        );
+        assert_eq!(
+            std::fs::read(out.join("2 a - b (fork)/a.osu")).unwrap(),
+            b"2 a - b (fork)"
+        );
+        assert_eq!(
+            std::fs::read(out.join("2 a - b (fork)/sb/bg.png")).unwrap(),
+            vec![7; 1500]
+        );
+
+        // Editing a linked copy's source mustn't leak into the file it was linked to.
+        std::fs::write(folders[1].folder.join("sb/bg.png"), vec![8; 1500]).unwrap();
+        let summary = archiver
+            .write(&folders, &Progress::default(), |_, _| {})
+            .unwrap();
+        assert_eq!(summary.unchanged_files, 3);
+        assert_eq!(summary.deduplicated_files, 0);
+        assert_eq!(
+            std::fs::read(out.join("1 a - b/sb/bg.png")).unwrap(),
+            vec![7; 1500]
+        );
+        assert_eq!(
+            std::fs::read(out.join("2 a - b (fork)/sb/bg.png")).unwrap(),
+            vec![8; 1500]
+        );
+    }
+}
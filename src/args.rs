@@ -0,0 +1,352 @@
+use std::{path::PathBuf, str::FromStr};
+
+use clap::{Parser, Subcommand};
+use osu_unsubmitted_extractor::{
+    api,
+    archive::{MirrorStrategy, TarFormat},
+    classify::Selection,
+    login,
+};
+use secrecy::SecretString;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Read the local beatmaps and write what was found to a manifest. Needs no API access.
+    Scan(ScanArgs),
+    /// Ask the API about the IDs in a manifest and record the answers in it.
+    Check(CheckArgs),
+    /// Archive the folders a checked manifest selects.
+    Pack(PackArgs),
+    /// Summarise what a manifest found and what `pack` would take.
+    Report(ReportArgs),
+    /// Scan, check and pack in one go.
+    Run(Box<RunArgs>),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[arg(short, long)]
+    /// Where to write the manifest.
+    pub manifest: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CheckArgs {
+    #[arg(short, long)]
+    /// The manifest written by `scan`, updated in place.
+    pub manifest: PathBuf,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PackArgs {
+    #[arg(short, long)]
+    /// A manifest that has been through `check`.
+    pub manifest: PathBuf,
+
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ReportArgs {
+    #[arg(short, long)]
+    pub manifest: PathBuf,
+
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SourceArgs {
+    #[arg(short, long, required_unless_present = "lazer_dir")]
+    /// The osu!stable Songs folder.
+    pub songs_folder: Option<PathBuf>,
+
+    #[arg(long, conflicts_with = "songs_folder", requires = "lazer_realm_export")]
+    /// An osu!lazer data directory, the one holding client.realm and files/, to read instead of a Songs folder.
+    pub lazer_dir: Option<PathBuf>,
+
+    #[arg(long, requires = "lazer_dir")]
+    /// A JSON export of the lazer realm's BeatmapSet table, see the lazer module docs for its shape.
+    pub lazer_realm_export: Option<PathBuf>,
+
+    #[arg(long, conflicts_with = "lazer_dir")]
+    /// Take local beatmap details from this osu!.db instead of reading every .osu file.
+    /// Files it doesn't list are still read.
+    pub osu_db: Option<PathBuf>,
+
+    #[arg(long, conflicts_with = "lazer_dir")]
+    /// How many threads read .osu files at once. Defaults to the number of CPUs.
+    pub scan_workers: Option<usize>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ApiArgs {
+    #[arg(long, required_unless_present = "offline")]
+    /// Your client secret. Created here: https://osu.ppy.sh/home/account/edit#oauth
+    pub secret: Option<SecretString>,
+
+    #[arg(short, long, required_unless_present = "offline")]
+    /// Your client id. Created here: https://osu.ppy.sh/home/account/edit#oauth
+    pub client_id: Option<u64>,
+
+    #[arg(long)]
+    /// Don't contact the API at all. Difficulties are judged from their own markers, osu!.db's
+    /// ranked status and the status cache, anything those can't settle is marked unverified.
+    pub offline: bool,
+
+    #[arg(short, long, default_value_t=reqwest::Url::from_str(api::BEATMAPS_URL).unwrap())]
+    pub lookup_beatmap_api_url: reqwest::Url,
+
+    #[arg(long, default_value_t=reqwest::Url::from_str(api::BEATMAPSETS_URL).unwrap())]
+    pub lookup_beatmapset_api_url: reqwest::Url,
+
+    #[arg(short, long, default_value_t = 50)]
+    pub rate_limit_per_minute: u64,
+
+    #[arg(long, default_value_t = 5)]
+    /// How many requests may go out back to back before the rate limit applies.
+    pub rate_limit_burst: u32,
+
+    #[arg(long, default_value_t = 4)]
+    /// How many beatmap lookups may be in flight at once.
+    pub max_concurrent_requests: usize,
+
+    #[arg(long, default_value_t = 5)]
+    /// Attempts per API request before giving up, transient failures are retried with backoff.
+    pub max_attempts: u32,
+
+    #[arg(long, default_value_t = 1)]
+    /// How many extra passes to make over IDs the API couldn't answer for.
+    pub unknown_retry_passes: u32,
+
+    #[arg(long)]
+    /// Where to keep API answers between runs. Defaults to the user cache directory.
+    pub cache_path: Option<PathBuf>,
+
+    #[arg(long, conflicts_with = "cache_path")]
+    /// Ask the API about every ID, without reading or writing the status cache.
+    pub no_cache: bool,
+
+    #[arg(long, conflicts_with = "offline")]
+    /// Log in as yourself in the browser so your own maps can be told apart by their Creator.
+    /// Set the OAuth application's callback URL to http://127.0.0.1:<redirect port>/callback.
+    pub login: bool,
+
+    #[arg(long, default_value_t = login::DEFAULT_REDIRECT_PORT)]
+    /// The port to listen on for the browser coming back from logging in.
+    pub redirect_port: u16,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SelectionArgs {
+    #[arg(long)]
+    /// Also extract submitted difficulties whose local .osu no longer matches the uploaded one.
+    pub include_modified: bool,
+
+    #[arg(long)]
+    /// Also extract difficulties whose submission status couldn't be determined (API/network failures).
+    pub include_unknown: bool,
+
+    #[arg(long)]
+    /// Also extract difficulties an `--offline` check couldn't verify either way.
+    pub include_unverified: bool,
+
+    #[arg(long)]
+    /// Write every scanned folder and difficulty, its status and why it was or wasn't selected
+    /// to this JSON file.
+    pub report_json: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Only extract difficulties you made yourself, as recorded by `--login` when checking.
+    pub only_mine: bool,
+}
+
+impl SelectionArgs {
+    pub fn selection(&self) -> Selection {
+        Selection {
+            include_modified: self.include_modified,
+            include_unknown: self.include_unknown,
+            include_unverified: self.include_unverified,
+            only_own: self.only_mine,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct OutputArgs {
+    #[arg(short, long, required_unless_present_any = ["osz_dir", "output_dir"])]
+    pub output_tar_path: Option<PathBuf>,
+
+    #[arg(long, conflicts_with = "output_tar_path")]
+    /// Write one importable .osz per song folder into this directory instead of a single tar.
+    pub osz_dir: Option<PathBuf>,
+
+    #[arg(long, conflicts_with_all = ["output_tar_path", "osz_dir"])]
+    /// Copy the song folders into this directory as they are, shaped like a Songs folder.
+    /// Files already there with the same contents are left alone, so re-running only writes
+    /// what changed.
+    pub output_dir: Option<PathBuf>,
+
+    #[arg(long, value_enum, requires = "output_dir", conflicts_with_all = ["output_tar_path", "osz_dir"])]
+    /// How --output-dir gets its files, copies by default. Hard links need the same
+    /// filesystem as the Songs folder, and editing either side then edits both. Reflinks fall
+    /// back to copies where the filesystem can't clone files.
+    pub mirror: Option<Mirror>,
+
+    #[arg(long)]
+    /// Continue an interrupted run from the journal kept next to the output.
+    pub resume: bool,
+
+    #[arg(long, conflicts_with = "resume")]
+    /// Work out what would be archived and how big it would be, without writing anything to
+    /// the output path.
+    pub dry_run: bool,
+
+    #[arg(long)]
+    /// Only extract the selected .osu files and the assets they reference, not whole folders.
+    pub only_unsubmitted_difficulties: bool,
+
+    #[arg(long)]
+    /// Write every file in full, even ones identical to a file already written. Otherwise
+    /// they're stored as hard links, which some older tar extractors don't understand.
+    pub no_dedup: bool,
+
+    #[arg(long, value_enum, conflicts_with_all = ["osz_dir", "output_dir"])]
+    /// How to compress the tar. Taken from the output's extension (.tar.gz, .tar.zst or
+    /// .tar.xz) if not given.
+    pub format: Option<Format>,
+
+    #[arg(long, allow_negative_numbers = true, conflicts_with_all = ["osz_dir", "output_dir"])]
+    /// 0-9 for gzip and xz, 1-22 for zstd. Defaults to the format's usual level.
+    pub compression_level: Option<i32>,
+
+    #[arg(long, conflicts_with_all = ["osz_dir", "output_dir"])]
+    /// How many threads zstd compresses on. Defaults to the number of CPUs.
+    pub compression_workers: Option<u32>,
+
+    #[arg(long, value_parser = parse_size, conflicts_with_all = ["osz_dir", "output_dir"])]
+    /// Split the tar into volumes of at most this size, e.g. `2GiB` or `25MB`, as
+    /// songs.001.tar, songs.002.tar, ... with songs.index.json listing what's in each.
+    /// Song folders are never split.
+    pub max_volume_size: Option<u64>,
+}
+
+/// A byte count with an optional unit. KB, MB, GB and TB are powers of 1000, KiB, MiB, GiB
+/// and TiB (or just K, M, G and T) powers of 1024.
+fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let unit_start = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(unit_start);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("`{size}` doesn't start with a number"))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000_u64.pow(2),
+        "gb" => 1000_u64.pow(3),
+        "tb" => 1000_u64.pow(4),
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(format!("unknown unit `{unit}`")),
+    };
+    match (number * multiplier as f64) as u64 {
+        0 => Err(format!(
+            "`{size}` rounds down to 0 bytes, the size must be at least 1"
+        )),
+        bytes => Ok(bytes),
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum Format {
+    Tar,
+    #[value(name = "tar.gz")]
+    TarGz,
+    #[value(name = "tar.zst")]
+    TarZst,
+    #[value(name = "tar.xz")]
+    TarXz,
+}
+
+impl From<Format> for TarFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Tar => TarFormat::Plain,
+            Format::TarGz => TarFormat::Gzip,
+            Format::TarZst => TarFormat::Zstd,
+            Format::TarXz => TarFormat::Xz,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum Mirror {
+    Copy,
+    Hardlink,
+    Reflink,
+}
+
+impl From<Mirror> for MirrorStrategy {
+    fn from(mirror: Mirror) -> Self {
+        match mirror {
+            Mirror::Copy => MirrorStrategy::Copy,
+            Mirror::Hardlink => MirrorStrategy::Hardlink,
+            Mirror::Reflink => MirrorStrategy::Reflink,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("25MB"), Ok(25_000_000));
+        assert_eq!(parse_size("2 GiB"), Ok(2 << 30));
+        assert_eq!(parse_size("1.5k"), Ok(1536));
+        assert_eq!(parse_size("100"), Ok(100));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("0GiB").is_err());
+        assert!(parse_size("0.4").is_err());
+        assert!(parse_size("GiB").is_err());
+        assert!(parse_size("2 PB").is_err());
+    }
+}
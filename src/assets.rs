@@ -0,0 +1,279 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    osu_file::{self, Event, OsuFile},
+    song_folder::FolderFile,
+};
+
+const SAMPLE_SETS: [&str; 3] = ["normal", "soft", "drum"];
+const SAMPLE_EXTENSIONS: [&str; 3] = ["wav", "ogg", "mp3"];
+
+/// Picks out the files of a song folder that the given `.osu` files need: the difficulties
+/// themselves, their audio, background/video, storyboard (including any shared `.osb`) and
+/// custom hitsounds. Everything else in the folder is left behind.
+///
+/// `difficulties` are paths inside `folder`, matched against the names in `folder_files`.
+pub fn select_files(
+    folder: &Path,
+    folder_files: &[FolderFile],
+    difficulties: &[PathBuf],
+) -> Vec<FolderFile> {
+    // Lookups are case insensitive as the game was written for Windows.
+    let by_name: HashMap<String, &FolderFile> = folder_files
+        .iter()
+        .filter_map(|f| Some((normalize(f.name.to_str()?), f)))
+        .collect();
+
+    let mut wanted_names = BTreeSet::new();
+    let mut sample_indices = HashSet::new();
+    let mut selected: BTreeSet<FolderFile> = BTreeSet::new();
+
+    for difficulty in difficulties {
+        let Some(file) = difficulty
+            .strip_prefix(folder)
+            .ok()
+            .and_then(|name| by_name.get(&normalize(name.to_str()?)))
+        else {
+            log::error!("Difficulty {difficulty:?} not found in {folder:?}");
+            continue;
+        };
+        selected.insert((*file).clone());
+        // Old maps often have metadata in a legacy code page, which mustn't cost them their
+        // assets.
+        let contents = match std::fs::read(&file.source) {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::error!("Failed reading file {difficulty:?}: {e:?}");
+                continue;
+            }
+        };
+        match OsuFile::parse_lenient(&String::from_utf8_lossy(&contents)) {
+            Ok((osu_file, _)) => {
+                wanted_names.extend(referenced_files(&osu_file));
+                sample_indices.extend(custom_sample_indices(&osu_file));
+            }
+            Err(e) => log::error!("Failed parsing file {difficulty:?}: {e}"),
+        }
+    }
+
+    for osb in folder_files.iter().filter(|f| {
+        f.name
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("osb"))
+    }) {
+        selected.insert(osb.clone());
+        match std::fs::read(&osb.source) {
+            Ok(contents) => {
+                let (events, _) = osu_file::parse_storyboard(&String::from_utf8_lossy(&contents));
+                wanted_names.extend(events.iter().flat_map(event_files));
+            }
+            Err(e) => log::error!("Failed reading storyboard {:?}: {e:?}", osb.source),
+        }
+    }
+
+    for name in &wanted_names {
+        match by_name.get(&normalize(name)) {
+            Some(file) => {
+                selected.insert((*file).clone());
+            }
+            None => log::debug!("Referenced file {name:?} not found in {folder:?}"),
+        }
+    }
+
+    // Custom hitsounds are only ever looked up from the top of the song folder.
+    selected.extend(
+        folder_files
+            .iter()
+            .filter(|f| f.name.components().count() == 1)
+            .filter(|f| {
+                f.name
+                    .to_str()
+                    .is_some_and(|n| is_custom_hitsound(n, &sample_indices))
+            })
+            .cloned(),
+    );
+
+    selected.into_iter().collect()
+}
+
+/// Files named by the difficulty itself, as written (relative to the song folder).
+pub fn referenced_files(osu_file: &OsuFile) -> BTreeSet<String> {
+    let mut files = BTreeSet::new();
+    if !osu_file.general.audio_filename.is_empty() {
+        files.insert(osu_file.general.audio_filename.clone());
+    }
+    files.extend(osu_file.events.iter().flat_map(event_files));
+    files.extend(
+        osu_file
+            .hit_objects
+            .iter()
+            .filter_map(|h| h.hit_sample.filename.clone()),
+    );
+    files
+}
+
+/// The custom sample indices the difficulty uses. Index 0 means the skin's samples.
+pub fn custom_sample_indices(osu_file: &OsuFile) -> HashSet<u32> {
+    osu_file
+        .timing_points
+        .iter()
+        .map(|t| t.sample_index)
+        .chain(osu_file.hit_objects.iter().map(|h| h.hit_sample.index))
+        .filter(|index| *index > 0)
+        .collect()
+}
+
+/// Whether `file_name` is a beatmap hitsound (e.g. `soft-hitclap3.wav`) for one of `indices`.
+/// Index 1 is stored without a number.
+pub fn is_custom_hitsound(file_name: &str, indices: &HashSet<u32>) -> bool {
+    let file_name = file_name.to_lowercase();
+    let Some((stem, extension)) = file_name.rsplit_once('.') else {
+        return false;
+    };
+    if !SAMPLE_EXTENSIONS.contains(&extension) {
+        return false;
+    }
+    let Some((sample_set, sample)) = stem.split_once('-') else {
+        return false;
+    };
+    if !SAMPLE_SETS.contains(&sample_set) {
+        return false;
+    }
+    let digits_start = sample.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (name, index) = sample.split_at(digits_start);
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return false;
+    }
+    let index = if index.is_empty() {
+        1
+    } else {
+        match index.parse() {
+            Ok(index) => index,
+            Err(_) => return false,
+        }
+    };
+    indices.contains(&index)
+}
+
+fn event_files(event: &Event) -> Vec<String> {
+    match event {
+        // Animations are stored one file per frame, numbered before the extension.
+        Event::Animation {
+            filepath,
+            frame_count,
+            ..
+        } => {
+            let (stem, extension) = filepath
+                .rsplit_once('.')
+                .map_or((filepath.as_str(), ""), |(s, e)| (s, e));
+            (0..*frame_count)
+                .map(|i| format!("{stem}{i}.{extension}"))
+                .collect()
+        }
+        _ => event.filename().map(str::to_owned).into_iter().collect(),
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.replace('\\', "/")
+        .trim_start_matches("./")
+        .to_lowercase()
+}
+
+pub(crate) fn files_in_dir_recursive(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            log::error!("Failed to read dir: {dir:?}");
+            continue;
+        };
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => dirs.push(entry.path()),
+                Ok(t) if t.is_file() => files.push(entry.path()),
+                _ => {}
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_referenced_files() {
+        let contents = "osu file format v14
+[General]
+AudioFilename: audio.mp3
+[Events]
+0,0,\"BG.jpg\",0,0
+Animation,Foreground,Centre,\"sb\\fire.png\",320,240,2,100
+[TimingPoints]
+0,500,4,2,3,60,1,0
+[HitObjects]
+256,192,0,1,0,0:0:0:0:clap.wav
+";
+        let osu_file = OsuFile::parse(contents).unwrap();
+        let files: Vec<String> = referenced_files(&osu_file).into_iter().collect();
+        assert_eq!(
+            files,
+            vec![
+                "BG.jpg",
+                "audio.mp3",
+                "clap.wav",
+                "sb\\fire0.png",
+                "sb\\fire1.png"
+            ]
+        );
+        assert_eq!(custom_sample_indices(&osu_file), HashSet::from([3]));
+    }
+
+    #[test]
+    fn test_is_custom_hitsound() {
+        let indices = HashSet::from([1, 3]);
+        assert!(is_custom_hitsound("soft-hitclap.wav", &indices));
+        assert!(is_custom_hitsound("Normal-HitWhistle3.ogg", &indices));
+        assert!(!is_custom_hitsound("drum-hitnormal2.wav", &indices));
+        assert!(!is_custom_hitsound("soft-hitclap3.png", &indices));
+        assert!(!is_custom_hitsound("applause.wav", &indices));
+    }
+
+    #[test]
+    fn test_select_files_non_utf8() {
+        let folder = std::env::temp_dir().join(format!("assets-test-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let files: Vec<FolderFile> = [
+            ("a.osu", &b"osu file format v14\n[General]\nAudioFilename: audio.mp3\n[Metadata]\nTitle:caf\xe9\n[Events]\n0,0,\"bg.jpg\",0,0\n"[..]),
+            ("audio.mp3", b""),
+            ("bg.jpg", b""),
+            ("unused.png", b""),
+        ]
+        .into_iter()
+        .map(|(name, contents)| {
+            std::fs::write(folder.join(name), contents).unwrap();
+            FolderFile {
+                source: folder.join(name),
+                name: PathBuf::from(name),
+            }
+        })
+        .collect();
+
+        let selected = select_files(&folder, &files, &[folder.join("a.osu")]);
+        std::fs::remove_dir_all(&folder).unwrap();
+        let names: Vec<&Path> = selected.iter().map(|f| f.name.as_path()).collect();
+        assert_eq!(
+            names,
+            vec![
+                Path::new("a.osu"),
+                Path::new("audio.mp3"),
+                Path::new("bg.jpg")
+            ]
+        );
+    }
+}
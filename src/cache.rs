@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{spec::web::Beatmap, submission::SubmissionStatus};
+
+const CACHE_VERSION: u32 = 2;
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// On-disk record of API answers so re-runs only ask about new or stale IDs.
+#[derive(Serialize, Deserialize)]
+pub struct StatusCache {
+    version: u32,
+    entries: HashMap<u64, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// The MD5s of every local .osu claiming the ID when this was fetched, sorted. Only kept for
+    /// answers with a checksum to compare them with. A local edit or a new copy invalidates the
+    /// entry.
+    local_md5s: Option<Vec<String>>,
+    /// Seconds since the unix epoch.
+    fetched_at: u64,
+    result: CachedResult,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "beatmap", rename_all = "snake_case")]
+enum CachedResult {
+    Submitted(Beatmap),
+    NotFound,
+}
+
+impl Default for StatusCache {
+    fn default() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl StatusCache {
+    /// `<user cache dir>/osu-unsubmitted-extractor/status_cache.json`.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::cache_dir()?
+                .join("osu-unsubmitted-extractor")
+                .join("status_cache.json"),
+        )
+    }
+
+    /// Loads the cache, starting afresh if it's missing, unreadable or from another version.
+    pub fn load(path: &Path) -> Self {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                log::warn!("Couldn't open status cache {path:?}, starting empty: {e}");
+                return Self::default();
+            }
+        };
+        match serde_json::from_reader::<_, Self>(BufReader::new(file)) {
+            Ok(cache) if cache.version == CACHE_VERSION => cache,
+            Ok(_) => {
+                log::warn!("Status cache {path:?} is from another version, starting empty");
+                Self::default()
+            }
+            Err(e) => {
+                log::warn!("Couldn't parse status cache {path:?}, starting empty: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes to a temporary file first so an interrupted save can't corrupt the cache.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        serde_json::to_writer(BufWriter::new(File::create(&tmp_path)?), self)?;
+        std::fs::rename(tmp_path, path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The cached answer for `id`, if there is one that hasn't expired for these versions of
+    /// its local copies. `local_md5s` is only called for answers recorded with them.
+    pub fn get(
+        &self,
+        id: u64,
+        local_md5s: impl FnOnce() -> Option<Vec<String>>,
+        now: SystemTime,
+    ) -> Option<SubmissionStatus> {
+        let entry = self.entries.get(&id)?;
+        if entry.local_md5s.is_some() && entry.local_md5s != local_md5s().map(sorted_md5s) {
+            return None;
+        }
+        if let Some(ttl) = entry.result.ttl() {
+            let age = unix_seconds(now).saturating_sub(entry.fetched_at);
+            if age > ttl.as_secs() {
+                return None;
+            }
+        }
+        Some(match &entry.result {
+            CachedResult::Submitted(beatmap) => SubmissionStatus::Submitted(beatmap.clone()),
+            CachedResult::NotFound => SubmissionStatus::NotFound,
+        })
+    }
+
+    /// Records an API answer for every local copy of `id`. `Unknown` isn't an answer, so it's
+    /// never cached. `local_md5s` is only called for answers with a checksum, and if it has
+    /// none to give nothing is cached.
+    pub fn insert(
+        &mut self,
+        id: u64,
+        local_md5s: impl FnOnce() -> Option<Vec<String>>,
+        status: &SubmissionStatus,
+        now: SystemTime,
+    ) {
+        let (result, local_md5s) = match status {
+            SubmissionStatus::Submitted(beatmap) if beatmap.checksum.is_some() => {
+                let Some(local_md5s) = local_md5s() else {
+                    return;
+                };
+                (
+                    CachedResult::Submitted(beatmap.clone()),
+                    Some(sorted_md5s(local_md5s)),
+                )
+            }
+            SubmissionStatus::Submitted(beatmap) => {
+                (CachedResult::Submitted(beatmap.clone()), None)
+            }
+            SubmissionStatus::NotFound => (CachedResult::NotFound, None),
+            SubmissionStatus::Unknown => return,
+        };
+        self.entries.insert(
+            id,
+            CacheEntry {
+                local_md5s,
+                fetched_at: unix_seconds(now),
+                result,
+            },
+        );
+    }
+}
+
+impl CachedResult {
+    /// How long an answer stays valid, `None` for forever.
+    fn ttl(&self) -> Option<Duration> {
+        match self {
+            CachedResult::Submitted(beatmap) => match beatmap.status.as_str() {
+                // These never change again.
+                "ranked" | "approved" | "loved" => None,
+                "qualified" => Some(DAY),
+                _ => Some(7 * DAY),
+            },
+            // Could be uploaded at any moment.
+            CachedResult::NotFound => Some(DAY),
+        }
+    }
+}
+
+fn sorted_md5s(mut md5s: Vec<String>) -> Vec<String> {
+    md5s.sort_unstable();
+    md5s.dedup();
+    md5s
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn beatmap(status: &str) -> Beatmap {
+        serde_json::from_str(&format!(
+            r#"{{"beatmapset_id":1,"id":2,"status":"{status}","version":"Insane","checksum":"abc","beatmapset":{{"artist":"a","title":"t"}}}}"#
+        ))
+        .unwrap()
+    }
+
+    fn md5s<'a>(md5s: &'a [&str]) -> impl FnOnce() -> Option<Vec<String>> + 'a {
+        move || Some(md5s.iter().map(|md5| (*md5).to_owned()).collect())
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let later = now + 30 * DAY;
+        let mut cache = StatusCache::default();
+        cache.insert(
+            1,
+            md5s(&["md5"]),
+            &SubmissionStatus::Submitted(beatmap("ranked")),
+            now,
+        );
+        cache.insert(
+            2,
+            md5s(&["md5"]),
+            &SubmissionStatus::Submitted(beatmap("graveyard")),
+            now,
+        );
+        cache.insert(3, md5s(&["md5"]), &SubmissionStatus::NotFound, now);
+        cache.insert(4, md5s(&["md5"]), &SubmissionStatus::Unknown, now);
+
+        assert!(cache.get(1, md5s(&["md5"]), later).is_some());
+        assert!(cache.get(1, md5s(&["edited"]), later).is_none());
+        assert!(cache.get(2, md5s(&["md5"]), now + DAY).is_some());
+        assert!(cache.get(2, md5s(&["md5"]), later).is_none());
+        assert!(matches!(
+            cache.get(3, md5s(&["md5"]), now),
+            Some(SubmissionStatus::NotFound)
+        ));
+        assert!(cache.get(3, md5s(&["md5"]), later).is_none());
+        // Nothing to compare a missing set's files with, so they aren't hashed.
+        assert!(cache.get(3, || panic!("hashed"), now).is_some());
+        assert!(cache.get(4, md5s(&["md5"]), now).is_none());
+
+        let fork = SubmissionStatus::Submitted(beatmap("ranked"));
+        cache.insert(5, md5s(&["fork", "download"]), &fork, now);
+        assert!(cache.get(5, md5s(&["download", "fork"]), now).is_some());
+        assert!(cache.get(5, md5s(&["download"]), now).is_none());
+        assert!(cache
+            .get(5, md5s(&["download", "fork", "new copy"]), now)
+            .is_none());
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let now = SystemTime::now();
+        let mut cache = StatusCache::default();
+        cache.insert(
+            1,
+            md5s(&["md5"]),
+            &SubmissionStatus::Submitted(beatmap("loved")),
+            now,
+        );
+        cache.insert(3, md5s(&["md5"]), &SubmissionStatus::NotFound, now);
+        let json = serde_json::to_string(&cache).unwrap();
+        let cache: StatusCache = serde_json::from_str(&json).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(matches!(
+            cache.get(1, md5s(&["md5"]), now),
+            Some(SubmissionStatus::Submitted(b)) if b.status == "loved"
+        ));
+    }
+}
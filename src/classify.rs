@@ -0,0 +1,665 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    osu_db::RankedStatus,
+    song_folder::{LocalDifficulty, SetClassification, SongFolder},
+    submission::{SetStatus, SubmissionStatus},
+};
+
+/// Song folder -> the .osu files inside it that matched.
+pub type FolderSelection = HashMap<PathBuf, HashSet<PathBuf>>;
+
+/// Where each song folder ended up.
+#[derive(Debug, Default)]
+pub struct Classification {
+    pub unsubmitted: FolderSelection,
+    /// Submitted difficulties whose local .osu no longer matches the uploaded one.
+    pub modified: FolderSelection,
+    /// Difficulties the API couldn't answer for.
+    pub unknown: FolderSelection,
+    /// Difficulties an offline check had nothing local to go on for.
+    pub unverified: FolderSelection,
+    /// Folders holding difficulties from more than one uploaded set.
+    pub mixed_sets: HashSet<PathBuf>,
+    /// IDs claimed by more than one .osu, e.g. a downloaded copy and a local fork, with every
+    /// file claiming each. Each copy is classified on its own.
+    pub duplicate_ids: HashMap<u64, Vec<PathBuf>>,
+}
+
+/// Which classes of difficulties beyond the unsubmitted ones to extract.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Selection {
+    pub include_modified: bool,
+    pub include_unknown: bool,
+    pub include_unverified: bool,
+    /// Only difficulties whose `Creator:` is the logged-in user, see
+    /// [`Manifest::select`](crate::manifest::Manifest::select).
+    pub only_own: bool,
+}
+
+impl Classification {
+    /// The unsubmitted difficulties, plus the modified, unknown and unverified ones if selected.
+    pub fn select(self, selection: Selection) -> FolderSelection {
+        let mut selected = self.unsubmitted;
+        let mut extra_selections = Vec::new();
+        if selection.include_modified {
+            extra_selections.push(self.modified);
+        }
+        if selection.include_unknown {
+            extra_selections.push(self.unknown);
+        }
+        if selection.include_unverified {
+            extra_selections.push(self.unverified);
+        }
+        for (folder, difficulties) in extra_selections.into_iter().flatten() {
+            selected.entry(folder).or_default().extend(difficulties);
+        }
+        selected
+    }
+}
+
+/// Sorts scanned song folders into unsubmitted/modified/unknown, first from what the files
+/// themselves say and then from the API's answers for the IDs they claim.
+pub struct SubmissionClassifier {
+    song_folders: HashMap<PathBuf, SongFolder>,
+    /// ID -> every .osu claiming it.
+    ids_to_check: HashMap<u64, Vec<PathBuf>>,
+    /// Folders claiming a single uploaded set, looked up as a whole before their IDs.
+    folders_to_set_ids: HashMap<PathBuf, u64>,
+    classification: Classification,
+}
+
+impl SubmissionClassifier {
+    pub fn new(song_folders: impl IntoIterator<Item = SongFolder>) -> Self {
+        let mut classifier = Self {
+            song_folders: song_folders
+                .into_iter()
+                .map(|song_folder| (song_folder.path.clone(), song_folder))
+                .collect(),
+            ids_to_check: HashMap::new(),
+            folders_to_set_ids: HashMap::new(),
+            classification: Classification::default(),
+        };
+        classifier.classify_locally();
+        classifier
+    }
+
+    fn classify_locally(&mut self) {
+        let classification = &mut self.classification;
+        for song_folder in self.song_folders.values() {
+            match song_folder.set_classification() {
+                SetClassification::Unsubmitted => {
+                    log::info!("Found unsubmitted set: {:#?}", song_folder.path);
+                    classification
+                        .unsubmitted
+                        .entry(song_folder.path.clone())
+                        .or_default()
+                        .extend(song_folder.difficulties.iter().map(|d| d.path.clone()));
+                    continue;
+                }
+                SetClassification::Set(set_id) => {
+                    self.folders_to_set_ids
+                        .insert(song_folder.path.clone(), set_id);
+                }
+                SetClassification::Mixed(set_ids) => {
+                    log::warn!(
+                        "Found difficulties from multiple sets {set_ids:?}: {:#?}",
+                        song_folder.path
+                    );
+                    classification.mixed_sets.insert(song_folder.path.clone());
+                }
+                SetClassification::Unknown => {}
+            }
+            for difficulty in &song_folder.difficulties {
+                match difficulty.metadata.beatmap_id {
+                    Some(0) => {
+                        log::info!("Found unsubmitted: {:#?}", difficulty.path);
+                        classification
+                            .unsubmitted
+                            .entry(song_folder.path.clone())
+                            .or_default()
+                            .insert(difficulty.path.clone());
+                    }
+                    Some(id) if id > 0 => {
+                        self.ids_to_check
+                            .entry(id as u64)
+                            .or_default()
+                            .push(difficulty.path.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for (id, copies) in &self.ids_to_check {
+            if copies.len() < 2 {
+                continue;
+            }
+            let mut paths = copies.clone();
+            paths.sort();
+            log::warn!(
+                "Found {} .osu files claiming beatmap ID {id}, classifying each on its own: {paths:#?}",
+                paths.len()
+            );
+            classification.duplicate_ids.insert(*id, paths);
+        }
+    }
+
+    pub fn song_folders(&self) -> &HashMap<PathBuf, SongFolder> {
+        &self.song_folders
+    }
+
+    /// The uploaded IDs the API needs asking about, with the path of every local .osu claiming
+    /// each.
+    pub fn ids_to_check(&self) -> &HashMap<u64, Vec<PathBuf>> {
+        &self.ids_to_check
+    }
+
+    /// The MD5s of every local copy of `id`, see [`Self::copy_md5`]. `None` if any of them
+    /// couldn't be read.
+    pub fn local_md5s(&mut self, id: u64) -> Option<Vec<String>> {
+        let copies = self.ids_to_check.get(&id)?.clone();
+        copies.iter().map(|path| self.copy_md5(path)).collect()
+    }
+
+    /// The MD5 of the local .osu at `path`, hashed on first use and kept for later ones. `None`
+    /// if it couldn't be read, the reason is logged.
+    fn copy_md5(&mut self, path: &Path) -> Option<String> {
+        let difficulty = self
+            .song_folders
+            .get_mut(path.parent()?)?
+            .difficulties
+            .iter_mut()
+            .find(|d| d.path == path)?;
+        let md5 = difficulty.md5()?;
+        difficulty.md5 = Some(md5.clone());
+        Some(md5)
+    }
+
+    /// Records the API's answers, comparing each local copy of an ID with the upload
+    /// separately. IDs without an answer are left alone. Only the copies the API gave a
+    /// checksum for are hashed, one that can't be read is unknown.
+    pub fn apply_statuses(&mut self, statuses: &HashMap<u64, SubmissionStatus>) {
+        for (id, status) in statuses {
+            let Some(copies) = self.ids_to_check.get(id).cloned() else {
+                continue;
+            };
+            for path in copies {
+                let folder = path.parent().expect("checked");
+                let classification = &mut self.classification;
+                let beatmap = match status {
+                    SubmissionStatus::Submitted(beatmap) => beatmap,
+                    SubmissionStatus::NotFound => {
+                        log::info!("Unsubmitted found ({id}): {path:#?}");
+                        classification
+                            .unsubmitted
+                            .entry(folder.to_owned())
+                            .or_default()
+                            .insert(path.to_owned());
+                        continue;
+                    }
+                    SubmissionStatus::Unknown => {
+                        log::warn!("Couldn't determine submission status ({id}): {path:#?}");
+                        classification
+                            .unknown
+                            .entry(folder.to_owned())
+                            .or_default()
+                            .insert(path.to_owned());
+                        continue;
+                    }
+                };
+                if let Some(set_id) = self.folders_to_set_ids.get(folder) {
+                    if *set_id != beatmap.beatmapset_id {
+                        log::warn!(
+                            "Difficulty {id} belongs to set {} not {set_id}: {path:#?}",
+                            beatmap.beatmapset_id
+                        );
+                        classification.mixed_sets.insert(folder.to_owned());
+                    }
+                }
+                let Some(checksum) = &beatmap.checksum else {
+                    continue;
+                };
+                match self.copy_md5(&path) {
+                    Some(local_md5) if local_md5 == *checksum => {}
+                    Some(_) => {
+                        log::info!("Locally modified since upload found ({id}): {path:#?}");
+                        self.classification
+                            .modified
+                            .entry(folder.to_owned())
+                            .or_default()
+                            .insert(path.to_owned());
+                    }
+                    None => {
+                        log::warn!("Couldn't compare with the uploaded version ({id}): {path:#?}");
+                        self.classification
+                            .unknown
+                            .entry(folder.to_owned())
+                            .or_default()
+                            .insert(path.to_owned());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Settles the IDs `statuses` has no answer for without asking the API: copies osu!.db saw
+    /// as submitted are left alone, the rest are marked unverified and their IDs returned. Call
+    /// [`Self::statuses_from_db`] first so the IDs osu!.db saw as unsubmitted are settled.
+    pub fn settle_offline(&mut self, statuses: &HashMap<u64, SubmissionStatus>) -> Vec<u64> {
+        let mut unverified: Vec<u64> = self
+            .ids_to_check
+            .iter()
+            .filter(|(id, _)| !statuses.contains_key(*id))
+            .filter(|(_, copies)| copies.iter().any(|path| !self.submitted_per_db(path)))
+            .map(|(id, _)| *id)
+            .collect();
+        unverified.sort_unstable();
+        self.mark_unverified(&unverified);
+        unverified
+    }
+
+    /// Records IDs an offline check couldn't confirm either way. Copies of them osu!.db saw as
+    /// submitted stay unmarked.
+    pub fn mark_unverified(&mut self, ids: &[u64]) {
+        for id in ids {
+            let Some(copies) = self.ids_to_check.get(id) else {
+                continue;
+            };
+            for path in copies {
+                if self.submitted_per_db(path) {
+                    continue;
+                }
+                log::info!("Couldn't verify offline ({id}): {path:#?}");
+                self.classification
+                    .unverified
+                    .entry(path.parent().expect("checked").to_owned())
+                    .or_default()
+                    .insert(path.to_owned());
+            }
+        }
+    }
+
+    /// `NotFound` for the IDs `statuses` has no answer for whose every copy osu!.db saw as
+    /// unsubmitted, so an offline check settles them like the API would have.
+    pub fn statuses_from_db(
+        &self,
+        statuses: &HashMap<u64, SubmissionStatus>,
+    ) -> HashMap<u64, SubmissionStatus> {
+        self.ids_to_check
+            .iter()
+            .filter(|(id, _)| !statuses.contains_key(*id))
+            .filter(|(_, copies)| {
+                copies
+                    .iter()
+                    .all(|path| self.ranked_status(path) == Some(RankedStatus::Unsubmitted))
+            })
+            .map(|(id, _)| (*id, SubmissionStatus::NotFound))
+            .collect()
+    }
+
+    fn submitted_per_db(&self, path: &Path) -> bool {
+        self.ranked_status(path)
+            .is_some_and(RankedStatus::is_submitted)
+    }
+
+    fn ranked_status(&self, path: &Path) -> Option<RankedStatus> {
+        self.difficulty(path).and_then(|d| d.ranked_status)
+    }
+
+    fn difficulty(&self, path: &Path) -> Option<&LocalDifficulty> {
+        self.song_folders
+            .get(path.parent()?)?
+            .difficulties
+            .iter()
+            .find(|d| d.path == path)
+    }
+
+    /// The sets claimed by single-set folders, each once, leaving out those `statuses`
+    /// already shows exist. One lookup answers every difficulty of a set, and a set that
+    /// doesn't exist makes its folders unsubmitted as a whole.
+    pub fn sets_to_look_up(&self, statuses: &HashMap<u64, SubmissionStatus>) -> Vec<u64> {
+        let found: HashSet<u64> = statuses
+            .values()
+            .filter_map(|status| match status {
+                SubmissionStatus::Submitted(beatmap) => Some(beatmap.beatmapset_id),
+                _ => None,
+            })
+            .collect();
+        let mut set_ids: Vec<u64> = self
+            .folders_to_set_ids
+            .values()
+            .filter(|set_id| !found.contains(*set_id))
+            .copied()
+            .collect::<HashSet<u64>>()
+            .into_iter()
+            .collect();
+        set_ids.sort_unstable();
+        set_ids
+    }
+
+    /// The answers `sets` holds for IDs that need checking, so they needn't be looked up one
+    /// by one. IDs a set doesn't list are left out, they may belong to another set.
+    pub fn statuses_from_sets(
+        &self,
+        sets: &HashMap<u64, SetStatus>,
+    ) -> HashMap<u64, SubmissionStatus> {
+        sets.values()
+            .filter_map(|set| match set {
+                SetStatus::Submitted(beatmaps) => Some(beatmaps),
+                _ => None,
+            })
+            .flatten()
+            .filter(|beatmap| self.ids_to_check.contains_key(&beatmap.id))
+            .map(|beatmap| (beatmap.id, SubmissionStatus::Submitted(beatmap.clone())))
+            .collect()
+    }
+
+    /// Marks the folders claiming a set `sets` says doesn't exist, returning them.
+    pub fn mark_missing_sets(&mut self, sets: &HashMap<u64, SetStatus>) -> Vec<PathBuf> {
+        let mut missing: Vec<PathBuf> = self
+            .folders_to_set_ids
+            .iter()
+            .filter(|(_, set_id)| matches!(sets.get(*set_id), Some(SetStatus::NotFound)))
+            .map(|(path, _)| path.clone())
+            .collect();
+        missing.sort();
+        for path in &missing {
+            log::info!(
+                "Unsubmitted set found ({}): {path:#?}",
+                self.folders_to_set_ids[path]
+            );
+            self.mark_set_missing(path);
+        }
+        missing
+    }
+
+    /// The set `folder` claims doesn't exist, so every difficulty in it is unsubmitted.
+    pub fn mark_set_missing(&mut self, folder: &Path) {
+        let Some(song_folder) = self.song_folders.get(folder) else {
+            return;
+        };
+        self.classification
+            .unsubmitted
+            .entry(folder.to_owned())
+            .or_default()
+            .extend(song_folder.difficulties.iter().map(|d| d.path.clone()));
+    }
+
+    pub fn classification(&self) -> &Classification {
+        &self.classification
+    }
+
+    pub fn into_parts(self) -> (HashMap<PathBuf, SongFolder>, Classification) {
+        (self.song_folders, self.classification)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::osu_file::Metadata;
+
+    fn difficulty(folder: &str, name: &str, beatmap_id: i64, set_id: i64) -> LocalDifficulty {
+        LocalDifficulty {
+            path: PathBuf::from(folder).join(name),
+            metadata: Metadata {
+                beatmap_id: Some(beatmap_id),
+                beatmap_set_id: Some(set_id),
+                ..Default::default()
+            },
+            md5: Some("local".to_owned()),
+            ranked_status: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_classification() {
+        let folders = vec![
+            SongFolder {
+                path: PathBuf::from("new"),
+                difficulties: vec![difficulty("new", "a.osu", 0, -1)],
+                files: None,
+            },
+            SongFolder {
+                path: PathBuf::from("uploaded"),
+                difficulties: vec![
+                    difficulty("uploaded", "a.osu", 1, 10),
+                    difficulty("uploaded", "b.osu", 2, 10),
+                    difficulty("uploaded", "c.osu", 0, 10),
+                ],
+                files: None,
+            },
+            SongFolder {
+                path: PathBuf::from("deleted"),
+                difficulties: vec![difficulty("deleted", "a.osu", 3, 20)],
+                files: None,
+            },
+        ];
+        let mut classifier = SubmissionClassifier::new(folders);
+        assert_eq!(classifier.ids_to_check().len(), 3);
+        assert_eq!(classifier.sets_to_look_up(&HashMap::new()), vec![10, 20]);
+
+        let beatmap: crate::spec::web::Beatmap = serde_json::from_str(
+            r#"{"beatmapset_id":10,"id":1,"status":"ranked","version":"x","checksum":"server","beatmapset":{"artist":"a","title":"t"}}"#,
+        )
+        .unwrap();
+        let sets = HashMap::from([
+            (10, SetStatus::Submitted(vec![beatmap])),
+            (20, SetStatus::NotFound),
+        ]);
+        let mut statuses = classifier.statuses_from_sets(&sets);
+        assert_eq!(statuses.keys().collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(
+            classifier.mark_missing_sets(&sets),
+            vec![PathBuf::from("deleted")]
+        );
+        statuses.extend([
+            (2, SubmissionStatus::Unknown),
+            (3, SubmissionStatus::NotFound),
+        ]);
+        classifier.apply_statuses(&statuses);
+        assert_eq!(classifier.sets_to_look_up(&statuses), vec![20]);
+
+        let classification = classifier.classification();
+        assert_eq!(
+            classification.unsubmitted[Path::new("uploaded")],
+            HashSet::from([PathBuf::from("uploaded/c.osu")])
+        );
+        assert!(classification.unsubmitted.contains_key(Path::new("new")));
+        assert!(classification
+            .unsubmitted
+            .contains_key(Path::new("deleted")));
+        assert!(classification.modified.contains_key(Path::new("uploaded")));
+        assert!(classification.unknown.contains_key(Path::new("uploaded")));
+        assert!(classification.mixed_sets.is_empty());
+        assert!(classification.duplicate_ids.is_empty());
+
+        let mut fork = difficulty("fork", "a.osu", 1, 10);
+        fork.md5 = Some("server".to_owned());
+        let folders = vec![
+            SongFolder {
+                path: PathBuf::from("download"),
+                difficulties: vec![difficulty("download", "a.osu", 1, 10)],
+                files: None,
+            },
+            SongFolder {
+                path: PathBuf::from("fork"),
+                difficulties: vec![fork],
+                files: None,
+            },
+        ];
+        let mut classifier = SubmissionClassifier::new(folders);
+        assert_eq!(classifier.ids_to_check()[&1].len(), 2);
+        classifier.apply_statuses(&HashMap::from([(
+            1,
+            SubmissionStatus::Submitted(serde_json::from_str(
+                r#"{"beatmapset_id":10,"id":1,"status":"ranked","version":"x","checksum":"server","beatmapset":{"artist":"a","title":"t"}}"#,
+            ).unwrap()),
+        )]));
+        let classification = classifier.classification();
+        assert_eq!(
+            classification.duplicate_ids[&1],
+            vec![PathBuf::from("download/a.osu"), PathBuf::from("fork/a.osu")]
+        );
+        assert_eq!(
+            classification.modified.keys().collect::<Vec<_>>(),
+            vec![Path::new("download")]
+        );
+
+        // A copy that can't be read isn't taken for modified.
+        let mut unreadable = difficulty("unreadable", "a.osu", 1, 10);
+        unreadable.md5 = None;
+        let mut fork = difficulty("fork", "a.osu", 1, 10);
+        fork.md5 = Some("server".to_owned());
+        let mut classifier = SubmissionClassifier::new([
+            SongFolder {
+                path: PathBuf::from("unreadable"),
+                difficulties: vec![unreadable],
+                files: None,
+            },
+            SongFolder {
+                path: PathBuf::from("fork"),
+                difficulties: vec![fork],
+                files: None,
+            },
+        ]);
+        assert_eq!(classifier.local_md5s(1), None);
+        classifier.apply_statuses(&HashMap::from([(
+            1,
+            SubmissionStatus::Submitted(serde_json::from_str(
+                r#"{"beatmapset_id":10,"id":1,"status":"ranked","version":"x","checksum":"server","beatmapset":{"artist":"a","title":"t"}}"#,
+            ).unwrap()),
+        )]));
+        let classification = classifier.classification();
+        assert!(classification.modified.is_empty());
+        assert_eq!(
+            classification.unknown.keys().collect::<Vec<_>>(),
+            vec![Path::new("unreadable")]
+        );
+
+        let mut folder = SongFolder {
+            path: PathBuf::from("offline"),
+            difficulties: vec![
+                difficulty("offline", "a.osu", 4, 30),
+                difficulty("offline", "b.osu", 5, 30),
+                difficulty("offline", "c.osu", 6, 30),
+                difficulty("offline", "d.osu", 7, 30),
+            ],
+            files: None,
+        };
+        folder.difficulties[0].ranked_status = Some(RankedStatus::Ranked);
+        folder.difficulties[1].ranked_status = Some(RankedStatus::Unsubmitted);
+        let mut classifier = SubmissionClassifier::new([folder]);
+        let mut statuses = HashMap::from([(6, SubmissionStatus::NotFound)]);
+        let from_db = classifier.statuses_from_db(&statuses);
+        assert_eq!(from_db.keys().collect::<Vec<_>>(), vec![&5]);
+        statuses.extend(from_db);
+        classifier.apply_statuses(&statuses);
+        let unverified = classifier.settle_offline(&statuses);
+        assert_eq!(unverified, vec![7]);
+        assert_eq!(
+            classifier.classification().unsubmitted[Path::new("offline")],
+            HashSet::from([
+                PathBuf::from("offline/b.osu"),
+                PathBuf::from("offline/c.osu")
+            ])
+        );
+        assert_eq!(
+            classifier.classification().unverified[Path::new("offline")],
+            HashSet::from([PathBuf::from("offline/d.osu")])
+        );
+    }
+
+    #[test]
+    fn test_only_checksums_are_hashed() {
+        let dir = std::env::temp_dir().join(format!("classify-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let folder = dir.to_str().unwrap();
+        std::fs::write(dir.join("a.osu"), "osu file format v14").unwrap();
+        let mut difficulties = vec![
+            difficulty(folder, "a.osu", 1, 10),
+            difficulty(folder, "b.osu", 2, 10),
+            difficulty(folder, "c.osu", 3, 10),
+            difficulty(folder, "d.osu", 4, 10),
+        ];
+        for difficulty in &mut difficulties {
+            difficulty.md5 = None;
+        }
+        let mut classifier = SubmissionClassifier::new([SongFolder {
+            path: dir.clone(),
+            difficulties,
+            files: None,
+        }]);
+
+        let beatmap = |id: u64, checksum: Option<String>| {
+            let mut beatmap: crate::spec::web::Beatmap = serde_json::from_str(&format!(
+                r#"{{"beatmapset_id":10,"id":{id},"status":"ranked","version":"x","checksum":null,"beatmapset":{{"artist":"a","title":"t"}}}}"#
+            ))
+            .unwrap();
+            beatmap.checksum = checksum;
+            SubmissionStatus::Submitted(beatmap)
+        };
+        let md5 = format!("{:x}", md5::compute("osu file format v14"));
+        classifier.apply_statuses(&HashMap::from([
+            (1, beatmap(1, Some(md5.clone()))),
+            (2, beatmap(2, None)),
+            (3, SubmissionStatus::NotFound),
+            // Missing from disk, so it can't be compared.
+            (4, beatmap(4, Some(md5.clone()))),
+        ]));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let hashed: Vec<Option<String>> = classifier.song_folders()[&dir]
+            .difficulties
+            .iter()
+            .map(|d| d.md5.clone())
+            .collect();
+        assert_eq!(hashed, vec![Some(md5), None, None, None]);
+        let classification = classifier.classification();
+        assert!(classification.modified.is_empty());
+        assert_eq!(
+            classification.unknown[&dir],
+            HashSet::from([dir.join("d.osu")])
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let folder = |name: &str| {
+            HashMap::from([(
+                PathBuf::from(name),
+                HashSet::from([Path::new(name).join("a.osu")]),
+            )])
+        };
+        let selected = |include_modified, include_unknown| {
+            let classification = Classification {
+                unsubmitted: folder("unsubmitted"),
+                modified: folder("modified"),
+                unknown: folder("unknown"),
+                ..Default::default()
+            };
+            let selection = Selection {
+                include_modified,
+                include_unknown,
+                ..Default::default()
+            };
+            let mut folders: Vec<PathBuf> = classification.select(selection).into_keys().collect();
+            folders.sort();
+            folders
+        };
+
+        assert_eq!(selected(false, false), vec![PathBuf::from("unsubmitted")]);
+        assert_eq!(
+            selected(false, true),
+            vec![PathBuf::from("unknown"), PathBuf::from("unsubmitted")]
+        );
+        assert_eq!(
+            selected(true, false),
+            vec![PathBuf::from("modified"), PathBuf::from("unsubmitted")]
+        );
+    }
+}
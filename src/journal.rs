@@ -0,0 +1,245 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{archive::Progress, song_folder::Scan, submission::SubmissionStatus};
+
+const JOURNAL_VERSION: u32 = 1;
+
+/// One line of the journal. Entries are only ever appended, so a crash can at worst leave a
+/// torn last line, which is ignored on resume.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+enum Entry {
+    Started {
+        version: u32,
+        songs_folder: PathBuf,
+    },
+    Scanned(Scan),
+    /// The answers for one chunk of IDs. `Unknown`s are left out so a resumed run asks again.
+    Resolved {
+        statuses: Vec<(u64, SubmissionStatus)>,
+    },
+    /// Archiving (re)started from an empty output, earlier `Archived` entries no longer count.
+    ArchiveStarted,
+    Archived {
+        folder: PathBuf,
+        /// Where the tar stream ended after this folder, for truncating a torn tail on resume.
+        tar_offset: Option<u64>,
+    },
+}
+
+/// Checkpoints for the current run, kept next to the output so `--resume` can pick up after
+/// an interruption instead of starting over.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+/// What an interrupted run had already done.
+#[derive(Default)]
+pub struct ResumeState {
+    pub scan: Option<Scan>,
+    pub resolved: HashMap<u64, SubmissionStatus>,
+    pub archive: Progress,
+}
+
+impl Journal {
+    /// `<output>.journal`, next to the tar or directory being written.
+    pub fn path_for(output: &Path) -> PathBuf {
+        let mut name = output.file_name().unwrap_or(output.as_os_str()).to_owned();
+        name.push(".journal");
+        output.with_file_name(name)
+    }
+
+    /// Starts a fresh journal, discarding any previous one at `path`.
+    pub fn create(path: &Path, songs_folder: &Path) -> io::Result<Self> {
+        let mut journal = Self {
+            path: path.to_owned(),
+            file: File::create(path)?,
+        };
+        journal.append(&Entry::Started {
+            version: JOURNAL_VERSION,
+            songs_folder: songs_folder.to_owned(),
+        })?;
+        Ok(journal)
+    }
+
+    /// Replays the journal at `path` and reopens it for appending.
+    pub fn resume(path: &Path, songs_folder: &Path) -> io::Result<(Self, ResumeState)> {
+        let mut state = ResumeState::default();
+        let contents = std::fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        match lines.next().map(serde_json::from_str) {
+            Some(Ok(Entry::Started {
+                version,
+                songs_folder: journaled,
+            })) if version == JOURNAL_VERSION => {
+                if journaled != songs_folder {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("journal is for songs folder {journaled:?}"),
+                    ));
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not a journal from this version",
+                ))
+            }
+        }
+        for line in lines {
+            let entry = match serde_json::from_str(line) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    log::warn!("Ignoring unreadable journal entry in {path:?}: {e}");
+                    continue;
+                }
+            };
+            match entry {
+                Entry::Started { .. } => {}
+                Entry::Scanned(scan) => state.scan = Some(scan),
+                Entry::Resolved { statuses } => state.resolved.extend(statuses),
+                Entry::ArchiveStarted => {
+                    state.archive = Progress::default();
+                }
+                Entry::Archived { folder, tar_offset } => {
+                    state.archive.archived.insert(folder);
+                    state.archive.tar_offset = tar_offset;
+                }
+            }
+        }
+        let mut file = OpenOptions::new().append(true).open(path)?;
+        if !contents.ends_with('\n') {
+            // Don't let the next entry run on from a torn one.
+            file.write_all(b"\n")?;
+        }
+        Ok((
+            Self {
+                path: path.to_owned(),
+                file,
+            },
+            state,
+        ))
+    }
+
+    pub fn record_scan(&mut self, scan: &Scan) {
+        self.record(&Entry::Scanned(scan.clone()));
+    }
+
+    pub fn record_resolved(&mut self, statuses: &HashMap<u64, SubmissionStatus>) {
+        let statuses: Vec<(u64, SubmissionStatus)> = statuses
+            .iter()
+            .filter(|(_, status)| !status.is_unknown())
+            .map(|(id, status)| (*id, status.clone()))
+            .collect();
+        if !statuses.is_empty() {
+            self.record(&Entry::Resolved { statuses });
+        }
+    }
+
+    pub fn record_archive_started(&mut self) {
+        self.record(&Entry::ArchiveStarted);
+    }
+
+    pub fn record_archived(&mut self, folder: &Path, tar_offset: Option<u64>) {
+        self.record(&Entry::Archived {
+            folder: folder.to_owned(),
+            tar_offset,
+        });
+    }
+
+    /// The run completed, nothing is left to resume.
+    pub fn finish(self) -> io::Result<()> {
+        drop(self.file);
+        std::fs::remove_file(&self.path)
+    }
+
+    /// A journal that can't be written only costs the ability to resume, so just warn.
+    fn record(&mut self, entry: &Entry) {
+        if let Err(e) = self.append(entry) {
+            log::warn!("Couldn't write to journal {:?}: {e}", self.path);
+        }
+    }
+
+    fn append(&mut self, entry: &Entry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        // One write per entry, so an interruption tears at most the last line.
+        self.file.write_all(&line)?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::song_folder::SongFolder;
+
+    #[test]
+    fn test_journal_replay() {
+        let path = std::env::temp_dir().join(format!("journal-test-{}", std::process::id()));
+        let songs = Path::new("Songs");
+        let mut journal = Journal::create(&path, songs).unwrap();
+        journal.record_scan(&Scan {
+            song_dirs_count: 2,
+            files_count: 5,
+            osu_files_count: 3,
+            song_folders: vec![SongFolder {
+                path: PathBuf::from("Songs/1 a - b"),
+                difficulties: Vec::new(),
+                files: None,
+            }],
+        });
+        journal.record_resolved(&HashMap::from([
+            (1, SubmissionStatus::NotFound),
+            (2, SubmissionStatus::Unknown),
+        ]));
+        journal.record_archived(Path::new("Songs/0 stale"), Some(512));
+        journal.record_archive_started();
+        journal.record_archived(Path::new("Songs/1 a - b"), Some(2048));
+        drop(journal);
+        // A torn entry from an interrupted write.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"entry":"archived","fol"#).unwrap();
+        drop(file);
+
+        assert!(Journal::resume(&path, Path::new("Elsewhere")).is_err());
+        let (mut journal, state) = Journal::resume(&path, songs).unwrap();
+        assert_eq!(state.scan.unwrap().osu_files_count, 3);
+        assert!(matches!(
+            state.resolved.get(&1),
+            Some(SubmissionStatus::NotFound)
+        ));
+        assert!(!state.resolved.contains_key(&2));
+        assert_eq!(
+            state.archive.archived,
+            HashSet::from([PathBuf::from("Songs/1 a - b")])
+        );
+        assert_eq!(state.archive.tar_offset, Some(2048));
+
+        journal.record_archived(Path::new("Songs/2 c - d"), Some(4096));
+        drop(journal);
+        let (journal, state) = Journal::resume(&path, songs).unwrap();
+        assert_eq!(state.archive.archived.len(), 2);
+        assert_eq!(state.archive.tar_offset, Some(4096));
+        journal.finish().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_journal_path() {
+        assert_eq!(
+            Journal::path_for(Path::new("out/songs.tar")),
+            PathBuf::from("out/songs.tar.journal")
+        );
+    }
+}
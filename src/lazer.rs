@@ -0,0 +1,319 @@
+//! osu!lazer as a source of song folders.
+//!
+//! lazer doesn't keep a `Songs/` folder. Every file lives once in a content-addressed store,
+//! `<data dir>/files/<h>/<hh>/<sha256>`, and `client.realm` records which files make up which
+//! beatmap set under which name. Realm's file format has no Rust reader, so the realm is read
+//! from a JSON export of its `BeatmapSet` table instead, shaped like lazer's own schema:
+//!
+//! ```json
+//! {
+//!   "BeatmapSet": [
+//!     {
+//!       "ID": "5b1c6e7d-...",
+//!       "OnlineID": -1,
+//!       "DeletePending": false,
+//!       "Files": [{ "Filename": "audio.mp3", "File": { "Hash": "e3b0c442..." } }],
+//!       "Beatmaps": [
+//!         {
+//!           "OnlineID": -1,
+//!           "DifficultyName": "Insane",
+//!           "MD5Hash": "0cc175b9...",
+//!           "Hash": "ca978112...",
+//!           "Metadata": {
+//!             "Title": "...", "TitleUnicode": "...",
+//!             "Artist": "...", "ArtistUnicode": "...",
+//!             "Author": { "Username": "..." },
+//!             "Source": "...", "Tags": "..."
+//!           }
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Anything that can read realm files can write it, e.g. a short script against one of Realm's
+//! official SDKs using lazer's model names. Fields not listed are ignored, and everything but
+//! `Files`, `Beatmaps` and the hashes may be left out.
+//!
+//! Each set becomes a [`SongFolder`] whose `path` is a name under `<data dir>/sets/` that
+//! doesn't exist on disk, with its files pointing into the store. Sets without an online ID
+//! come out marked as never uploaded, the rest go through the same API checks as stable maps.
+
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    archive::sanitize_file_name,
+    osu_file::Metadata,
+    song_folder::{FolderFile, LocalDifficulty, Scan, SongFolder},
+};
+
+#[derive(Deserialize)]
+struct RealmExport {
+    #[serde(rename = "BeatmapSet")]
+    beatmap_sets: Vec<BeatmapSetInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BeatmapSetInfo {
+    #[serde(rename = "ID", default)]
+    id: String,
+    #[serde(rename = "OnlineID", default = "no_online_id")]
+    online_id: i64,
+    #[serde(default)]
+    delete_pending: bool,
+    files: Vec<NamedFileUsage>,
+    beatmaps: Vec<BeatmapInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NamedFileUsage {
+    filename: String,
+    file: RealmFile,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RealmFile {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BeatmapInfo {
+    #[serde(rename = "OnlineID", default = "no_online_id")]
+    online_id: i64,
+    #[serde(default)]
+    difficulty_name: String,
+    /// Older realms may not have it, the .osu in the store is hashed if it's needed then.
+    #[serde(rename = "MD5Hash", default)]
+    md5_hash: Option<String>,
+    /// SHA-256 of the .osu, which is also its name in the file store.
+    hash: String,
+    #[serde(default)]
+    metadata: BeatmapMetadata,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct BeatmapMetadata {
+    title: String,
+    title_unicode: String,
+    artist: String,
+    artist_unicode: String,
+    author: RealmUser,
+    source: String,
+    tags: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct RealmUser {
+    username: String,
+}
+
+fn no_online_id() -> i64 {
+    -1
+}
+
+/// A lazer data directory, read through an export of its realm.
+pub struct LazerStore {
+    data_dir: PathBuf,
+    export: RealmExport,
+}
+
+impl LazerStore {
+    pub fn open(data_dir: &Path, realm_export: &Path) -> io::Result<Self> {
+        let export = serde_json::from_reader(BufReader::new(File::open(realm_export)?))?;
+        Ok(Self {
+            data_dir: data_dir.to_owned(),
+            export,
+        })
+    }
+
+    /// Where the store keeps the file with this SHA-256.
+    pub fn file_path(&self, hash: &str) -> PathBuf {
+        self.data_dir
+            .join("files")
+            .join(hash.get(..1).unwrap_or_default())
+            .join(hash.get(..2).unwrap_or_default())
+            .join(hash)
+    }
+
+    /// Every set in the export as a song folder, skipping those pending deletion.
+    pub fn scan(&self) -> Scan {
+        let mut scan = Scan {
+            song_dirs_count: 0,
+            files_count: 0,
+            osu_files_count: 0,
+            song_folders: Vec::new(),
+        };
+        for set in self
+            .export
+            .beatmap_sets
+            .iter()
+            .filter(|s| !s.delete_pending)
+        {
+            scan.song_dirs_count += 1;
+            scan.files_count += set.files.len();
+            scan.osu_files_count += set.beatmaps.len();
+            scan.song_folders.push(self.song_folder(set));
+        }
+        log::info!(
+            "Read {} beatmap sets from the lazer realm export",
+            scan.song_folders.len()
+        );
+        scan
+    }
+
+    fn song_folder(&self, set: &BeatmapSetInfo) -> SongFolder {
+        let metadata = set.beatmaps.first().map(|b| &b.metadata);
+        let artist = metadata.map_or("", |m| m.artist.as_str());
+        let title = metadata.map_or("", |m| m.title.as_str());
+        // Named the way stable names downloaded sets, with the realm ID keeping local sets
+        // of the same song apart.
+        let folder_name = if set.online_id > 0 {
+            format!("{} {artist} - {title}", set.online_id)
+        } else {
+            format!(
+                "{artist} - {title} ({})",
+                set.id.get(..8).unwrap_or(&set.id)
+            )
+        };
+        let path = self
+            .data_dir
+            .join("sets")
+            .join(sanitize_file_name(&folder_name));
+
+        let files: Vec<FolderFile> = set
+            .files
+            .iter()
+            .map(|usage| FolderFile {
+                source: self.file_path(&usage.file.hash),
+                name: PathBuf::from(&usage.filename),
+            })
+            .collect();
+
+        let difficulties = set
+            .beatmaps
+            .iter()
+            .filter_map(|beatmap| {
+                let Some(usage) = set
+                    .files
+                    .iter()
+                    .find(|usage| usage.file.hash == beatmap.hash)
+                else {
+                    log::warn!(
+                        "No file for difficulty {:?} in {path:?}",
+                        beatmap.difficulty_name
+                    );
+                    return None;
+                };
+                Some(LocalDifficulty {
+                    path: path.join(&usage.filename),
+                    metadata: Metadata {
+                        title: beatmap.metadata.title.clone(),
+                        title_unicode: beatmap.metadata.title_unicode.clone(),
+                        artist: beatmap.metadata.artist.clone(),
+                        artist_unicode: beatmap.metadata.artist_unicode.clone(),
+                        creator: beatmap.metadata.author.username.clone(),
+                        version: beatmap.difficulty_name.clone(),
+                        source: beatmap.metadata.source.clone(),
+                        tags: beatmap
+                            .metadata
+                            .tags
+                            .split_whitespace()
+                            .map(str::to_owned)
+                            .collect(),
+                        // lazer uses -1 for both, stable's markers are what the classifier knows.
+                        beatmap_id: Some(beatmap.online_id.max(0)),
+                        beatmap_set_id: Some(if set.online_id > 0 { set.online_id } else { -1 }),
+                    },
+                    md5: beatmap.md5_hash.clone(),
+                    ranked_status: None,
+                    source: Some(self.file_path(&beatmap.hash)),
+                })
+            })
+            .collect();
+
+        SongFolder {
+            path,
+            difficulties,
+            files: Some(files),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::song_folder::SetClassification;
+
+    #[test]
+    fn test_lazer_scan() {
+        let export = r#"{"BeatmapSet": [
+            {
+                "ID": "5b1c6e7d-0000-0000-0000-000000000000",
+                "OnlineID": -1,
+                "Files": [
+                    {"Filename": "audio.mp3", "File": {"Hash": "aa11"}},
+                    {"Filename": "a (me) [Insane].osu", "File": {"Hash": "bb22"}}
+                ],
+                "Beatmaps": [{
+                    "OnlineID": -1,
+                    "DifficultyName": "Insane",
+                    "MD5Hash": "md5",
+                    "Hash": "bb22",
+                    "Metadata": {"Title": "Song", "Artist": "Band", "Author": {"Username": "me"}}
+                }]
+            },
+            {
+                "ID": "1",
+                "OnlineID": 123,
+                "Files": [{"Filename": "b.osu", "File": {"Hash": "cc33"}}],
+                "Beatmaps": [{"OnlineID": 456, "Hash": "cc33", "Metadata": {"Title": "T", "Artist": "A"}}]
+            },
+            {"ID": "2", "DeletePending": true, "Files": [], "Beatmaps": []}
+        ]}"#;
+        let data_dir = std::env::temp_dir().join(format!("lazer-test-{}", std::process::id()));
+        let store = LazerStore {
+            data_dir: data_dir.clone(),
+            export: serde_json::from_str(export).unwrap(),
+        };
+        // The uploaded set's difficulty has no MD5 in the export, so it's hashed from the store.
+        std::fs::create_dir_all(data_dir.join("files/c/cc")).unwrap();
+        std::fs::write(store.file_path("cc33"), "osu file format v14").unwrap();
+        let scan = store.scan();
+        assert_eq!(scan.song_dirs_count, 2);
+
+        let local = &scan.song_folders[0];
+        assert_eq!(local.path, data_dir.join("sets/Band - Song (5b1c6e7d)"));
+        assert_eq!(local.set_classification(), SetClassification::Unsubmitted);
+        assert_eq!(
+            local.difficulties[0].path,
+            local.path.join("a (me) [Insane].osu")
+        );
+        assert_eq!(local.difficulties[0].metadata.creator, "me");
+        assert_eq!(local.difficulties[0].md5(), Some("md5".to_owned()));
+        assert_eq!(local.files()[0].source, data_dir.join("files/a/aa/aa11"));
+
+        let uploaded = &scan.song_folders[1];
+        assert_eq!(uploaded.path, data_dir.join("sets/123 A - T"));
+        assert_eq!(uploaded.set_classification(), SetClassification::Set(123));
+        assert_eq!(uploaded.difficulties[0].metadata.beatmap_id, Some(456));
+        assert_eq!(uploaded.difficulties[0].md5, None);
+        assert_eq!(
+            uploaded.difficulties[0].md5(),
+            Some(format!("{:x}", md5::compute("osu file format v14")))
+        );
+        std::fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,28 @@
+//! Finds the beatmaps in a legacy osu! Songs folder that were never uploaded, and packs them.
+//!
+//! A run goes [`SongsScanner`] -> [`SubmissionClassifier`] (asking [`OsuApiClient`] about
+//! the IDs it can't settle locally) -> [`Archiver`].
+
+pub mod api;
+pub mod archive;
+pub mod assets;
+pub mod cache;
+pub mod classify;
+pub mod journal;
+pub mod lazer;
+pub mod login;
+pub mod manifest;
+pub mod osu_db;
+pub mod osu_file;
+pub mod rate_limit;
+pub mod retry;
+pub mod scanner;
+pub mod song_folder;
+pub mod spec;
+pub mod submission;
+pub mod token;
+
+pub use api::OsuApiClient;
+pub use archive::Archiver;
+pub use classify::SubmissionClassifier;
+pub use scanner::SongsScanner;
@@ -0,0 +1,391 @@
+//! Logging in as a user with the authorization code grant, so the tool can tell the user's own
+//! maps apart from everyone else's.
+//!
+//! The user opens the printed authorize URL, osu! sends the browser back to a listener on
+//! `http://127.0.0.1:<port>/callback`, and the code it carries is swapped for a token with the
+//! `identify` and `public` scopes. The OAuth application's callback URL has to be set to that
+//! same address. The token is kept in a file only the user can read, so later runs don't need
+//! the browser again until the refresh token stops working.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Url;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader},
+    net::TcpListener,
+};
+
+use crate::{
+    api::TOKEN_URL,
+    retry::{send_with_retry, RetryPolicy},
+    spec::web::{AuthorizationCodeRequest, RefreshTokenRequest, TokenResponse, User},
+};
+
+pub const AUTHORIZE_URL: &str = "https://osu.ppy.sh/oauth/authorize";
+pub const ME_URL: &str = "https://osu.ppy.sh/api/v2/me";
+pub const DEFAULT_REDIRECT_PORT: u16 = 7270;
+
+const SCOPES: &str = "identify public";
+/// How long to wait for the browser to come back before giving up.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// A user token as kept between runs.
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    /// Seconds since the unix epoch.
+    expires_at: u64,
+}
+
+impl StoredToken {
+    fn from_response(res: TokenResponse, requested_at: SystemTime) -> Self {
+        Self {
+            access_token: res.access_token,
+            refresh_token: res.refresh_token,
+            expires_at: unix_secs(requested_at) + res.expires_in,
+        }
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        unix_secs(now) >= self.expires_at
+    }
+}
+
+/// What the browser brought back to the listener.
+#[derive(Debug, PartialEq, Eq)]
+enum Callback {
+    Code(String),
+    /// osu! refused, e.g. the user pressed cancel.
+    Denied(String),
+    /// Some other request, like the browser asking for a favicon.
+    Other,
+}
+
+/// Finds out who the user is, logging them in through the browser if needed.
+pub struct Login {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    client_id: u64,
+    secret: SecretString,
+    redirect_port: u16,
+    token_path: Option<PathBuf>,
+}
+
+impl Login {
+    pub fn new(client_id: u64, secret: &SecretString, retry: RetryPolicy) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            retry,
+            client_id,
+            secret: secret.clone(),
+            redirect_port: DEFAULT_REDIRECT_PORT,
+            token_path: Self::default_token_path(),
+        }
+    }
+
+    /// `<user config dir>/osu-unsubmitted-extractor/user_token.json`.
+    pub fn default_token_path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("osu-unsubmitted-extractor")
+                .join("user_token.json"),
+        )
+    }
+
+    pub fn redirect_port(mut self, port: u16) -> Self {
+        self.redirect_port = port;
+        self
+    }
+
+    /// Where to keep the token between runs, `None` to log in every time.
+    pub fn token_path(mut self, path: Option<PathBuf>) -> Self {
+        self.token_path = path;
+        self
+    }
+
+    fn redirect_uri(&self) -> String {
+        format!("http://127.0.0.1:{}/callback", self.redirect_port)
+    }
+
+    /// The logged-in user. A stored token is used if it still works, refreshed if it has
+    /// expired, and the browser is only needed when neither gets anywhere. `None` if logging
+    /// in failed, the reason is logged.
+    pub async fn user(&self) -> Option<User> {
+        let stored = self.token_path.as_deref().and_then(load_token);
+        if let Some(stored) = stored {
+            if !stored.is_expired(SystemTime::now()) {
+                if let Some(user) = self.me(&stored.access_token).await {
+                    return Some(user);
+                }
+            }
+            if let Some(refresh_token) = &stored.refresh_token {
+                let body = serde_json::to_string(&RefreshTokenRequest::new(
+                    self.client_id,
+                    &self.secret,
+                    refresh_token,
+                ))
+                .expect("encodes");
+                if let Some(refreshed) = self.request_token(body).await {
+                    self.store(&refreshed);
+                    if let Some(user) = self.me(&refreshed.access_token).await {
+                        return Some(user);
+                    }
+                }
+            }
+            log::info!("Stored login no longer works, logging in again");
+        }
+
+        let token = self.authorize().await?;
+        self.store(&token);
+        self.me(&token.access_token).await
+    }
+
+    /// Has the user approve access in the browser and swaps the code for a token.
+    async fn authorize(&self) -> Option<StoredToken> {
+        let listener = match TcpListener::bind(("127.0.0.1", self.redirect_port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!(
+                    "Couldn't listen on port {} for the login redirect: {e}",
+                    self.redirect_port
+                );
+                return None;
+            }
+        };
+        let state = format!("{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..));
+        let redirect_uri = self.redirect_uri();
+        let url = authorize_url(self.client_id, &redirect_uri, &state);
+        log::info!("Open this link to log in, osu! will send you back here afterwards:");
+        log::info!("{url}");
+
+        let code =
+            match tokio::time::timeout(CALLBACK_TIMEOUT, wait_for_code(&listener, &state)).await {
+                Ok(Ok(code)) => code,
+                Ok(Err(e)) => {
+                    log::error!("Login failed: {e}");
+                    return None;
+                }
+                Err(_) => {
+                    log::error!(
+                        "Nobody logged in within {} minutes, giving up",
+                        CALLBACK_TIMEOUT.as_secs() / 60
+                    );
+                    return None;
+                }
+            };
+        let body = serde_json::to_string(&AuthorizationCodeRequest::new(
+            self.client_id,
+            &self.secret,
+            &code,
+            &redirect_uri,
+        ))
+        .expect("encodes");
+        self.request_token(body).await
+    }
+
+    async fn request_token(&self, body: String) -> Option<StoredToken> {
+        let requested_at = SystemTime::now();
+        let res = send_with_retry(&self.retry, || {
+            self.client
+                .post(Url::from_str(TOKEN_URL).expect("legit url"))
+                .body(body.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(reqwest::header::ACCEPT, "application/json")
+        })
+        .await;
+        let text = match res {
+            Ok(res) => res.text().await.ok()?,
+            Err(e) => {
+                log::error!("User token request failed: {e}");
+                return None;
+            }
+        };
+        match serde_json::from_str::<TokenResponse>(&text) {
+            Ok(res) => Some(StoredToken::from_response(res, requested_at)),
+            Err(_) => {
+                log::warn!("Didn't get a user token: {text}");
+                None
+            }
+        }
+    }
+
+    /// `None` if the token was refused or the request failed.
+    async fn me(&self, access_token: &str) -> Option<User> {
+        let res = send_with_retry(&self.retry, || {
+            self.client
+                .get(Url::from_str(ME_URL).expect("legit url"))
+                .header(reqwest::header::ACCEPT, "application/json")
+                .header(
+                    reqwest::header::AUTHORIZATION,
+                    format!("Bearer {access_token}"),
+                )
+        })
+        .await
+        .ok()?;
+        if !res.status().is_success() {
+            log::info!("Logged in user lookup got {}", res.status());
+            return None;
+        }
+        let user: User = serde_json::from_str(&res.text().await.ok()?).ok()?;
+        log::info!("Logged in as {}", user.username);
+        Some(user)
+    }
+
+    fn store(&self, token: &StoredToken) {
+        let Some(path) = &self.token_path else {
+            return;
+        };
+        if let Err(e) = save_token(path, token) {
+            log::warn!("Couldn't store login in {path:?}: {e}");
+        }
+    }
+}
+
+fn authorize_url(client_id: u64, redirect_uri: &str, state: &str) -> Url {
+    let mut url = Url::from_str(AUTHORIZE_URL).expect("legit url");
+    url.query_pairs_mut()
+        .append_pair("client_id", &client_id.to_string())
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("response_type", "code")
+        .append_pair("scope", SCOPES)
+        .append_pair("state", state);
+    url
+}
+
+/// Answers requests on `listener` until the browser comes back from osu!.
+async fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, String> {
+    loop {
+        let (stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+        let mut stream = AsyncBufReader::new(stream);
+        let mut request_line = String::new();
+        if stream.read_line(&mut request_line).await.is_err() {
+            continue;
+        }
+        let callback = parse_callback(&request_line, state);
+        let (status, message) = match &callback {
+            Callback::Code(_) => ("200 OK", "Logged in, you can close this tab."),
+            Callback::Denied(_) => ("200 OK", "Login was refused, you can close this tab."),
+            Callback::Other => ("404 Not Found", "Not found."),
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{message}",
+            message.len()
+        );
+        // The browser not getting the page doesn't change the outcome.
+        let _ = stream.get_mut().write_all(response.as_bytes()).await;
+        match callback {
+            Callback::Code(code) => return Ok(code),
+            Callback::Denied(reason) => return Err(reason),
+            Callback::Other => {}
+        }
+    }
+}
+
+/// Reads `GET /callback?code=...&state=... HTTP/1.1`.
+fn parse_callback(request_line: &str, state: &str) -> Callback {
+    let Some(target) = request_line
+        .strip_prefix("GET ")
+        .and_then(|rest| rest.split_whitespace().next())
+    else {
+        return Callback::Other;
+    };
+    let Ok(url) = Url::parse(&format!("http://127.0.0.1{target}")) else {
+        return Callback::Other;
+    };
+    if url.path() != "/callback" {
+        return Callback::Other;
+    }
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if param("state").as_deref() != Some(state) {
+        return Callback::Denied("the redirect didn't carry this login's state".to_owned());
+    }
+    match (param("code"), param("error")) {
+        (Some(code), _) => Callback::Code(code),
+        (None, error) => Callback::Denied(error.unwrap_or_else(|| "no code given".to_owned())),
+    }
+}
+
+fn load_token(path: &Path) -> Option<StoredToken> {
+    let file = File::open(path).ok()?;
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(token) => Some(token),
+        Err(e) => {
+            log::warn!("Couldn't parse stored login {path:?}, ignoring it: {e}");
+            None
+        }
+    }
+}
+
+/// Creates the file readable by its owner only, as the refresh token is as good as a password.
+fn save_token(path: &Path, token: &StoredToken) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies to new files.
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    serde_json::to_writer(BufWriter::new(options.open(path)?), token)?;
+    Ok(())
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_callback() {
+        assert_eq!(
+            parse_callback("GET /callback?code=abc%2B1&state=xyz HTTP/1.1\r\n", "xyz"),
+            Callback::Code("abc+1".to_owned())
+        );
+        assert_eq!(
+            parse_callback(
+                "GET /callback?error=access_denied&state=xyz HTTP/1.1\r\n",
+                "xyz"
+            ),
+            Callback::Denied("access_denied".to_owned())
+        );
+        assert!(matches!(
+            parse_callback("GET /callback?code=abc&state=other HTTP/1.1\r\n", "xyz"),
+            Callback::Denied(_)
+        ));
+        assert_eq!(
+            parse_callback("GET /favicon.ico HTTP/1.1\r\n", "xyz"),
+            Callback::Other
+        );
+        assert_eq!(parse_callback("garbage", "xyz"), Callback::Other);
+
+        let url = authorize_url(1, "http://127.0.0.1:7270/callback", "xyz");
+        assert!(url
+            .as_str()
+            .contains("redirect_uri=http%3A%2F%2F127.0.0.1%3A7270%2Fcallback"));
+        assert!(url.as_str().contains("scope=identify+public"));
+    }
+}
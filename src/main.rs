@@ -1,19 +1,27 @@
 use std::{
-    collections::{HashMap, HashSet},
-    fs::{read_to_string, Metadata},
+    collections::HashMap,
     path::{Path, PathBuf},
-    str::FromStr,
-    thread::sleep,
-    time::{Duration, Instant},
+    time::{Instant, SystemTime},
 };
 
+use args::{ApiArgs, Command, OutputArgs, SelectionArgs, SourceArgs};
 use clap::Parser;
-use itertools::Itertools;
-use log;
-use reqwest;
-use secrecy::SecretString;
+use osu_unsubmitted_extractor::{
+    archive::{self, Archiver, TarCompression, TarFormat},
+    cache,
+    classify::{Classification, FolderSelection},
+    journal::{self, Journal},
+    lazer::LazerStore,
+    login::Login,
+    manifest::{CheckResults, Manifest},
+    osu_db::OsuDb,
+    retry::RetryPolicy,
+    song_folder::Scan,
+    submission::SubmissionStatus,
+    OsuApiClient, SongsScanner, SubmissionClassifier,
+};
 
-mod spec;
+mod args;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -21,20 +29,239 @@ async fn main() {
     log::info!("Starting");
     let start_time = Instant::now();
 
-    let args = spec::args::Args::parse();
+    match args::Cli::parse().command {
+        Command::Scan(args) => {
+            let scan = scan(&args.source);
+            log_scan_summary(&scan);
+            save_manifest(
+                &Manifest::new(source_path(&args.source), scan),
+                &args.manifest,
+            );
+        }
+        Command::Check(args) => {
+            let mut manifest = load_manifest(&args.manifest);
+            let api = authenticate(&args.api, start_time).await;
+            let check = check(
+                &manifest.scan,
+                &args.api,
+                api.as_ref(),
+                HashMap::new(),
+                None,
+            )
+            .await;
+            manifest.check = Some(check);
+            save_manifest(&manifest, &args.manifest);
+        }
+        Command::Pack(args) => {
+            tar_compression(&args.output);
+            let manifest = load_manifest(&args.manifest);
+            if manifest.check.is_none() {
+                log::error!(
+                    "{:?} hasn't been checked against the API yet, run `check` on it first",
+                    args.manifest
+                );
+                std::process::exit(1);
+            }
+            if args.output.dry_run {
+                dry_run(&manifest, &args.selection, &args.output);
+            } else {
+                let (journal, resume_state) = open_journal(&args.output, &manifest.source);
+                pack(
+                    &manifest,
+                    &args.selection,
+                    &args.output,
+                    journal,
+                    resume_state.archive,
+                );
+            }
+        }
+        Command::Report(args) => {
+            let manifest = load_manifest(&args.manifest);
+            log_scan_summary(&manifest.scan);
+            if manifest.check.is_none() {
+                log::warn!("Not checked against the API yet, only local markers are reflected");
+            }
+            let (_, classification) = manifest.classify();
+            log_classification(&classification, &args.selection);
+            write_report(&manifest, &args.selection);
+            let selected = select(&manifest, classification, &args.selection);
+            log::info!("`pack` would archive {} song folders", selected.len());
+        }
+        Command::Run(args) => {
+            // Settled before the scan and API lookups rather than after them.
+            tar_compression(&args.output);
+            let source = source_path(&args.source);
+            // A dry run leaves no journal behind, nothing it does needs resuming.
+            let (mut journal, mut resume_state) = if args.output.dry_run {
+                (None, journal::ResumeState::default())
+            } else {
+                let (journal, resume_state) = open_journal(&args.output, &source);
+                (Some(journal), resume_state)
+            };
+            let api = authenticate(&args.api, start_time).await;
+            let scan = match resume_state.scan.take() {
+                Some(scan) => {
+                    log::info!(
+                        "Resuming with the {} song folders scanned by the interrupted run",
+                        scan.song_folders.len()
+                    );
+                    scan
+                }
+                None => {
+                    let scan = scan(&args.source);
+                    if let Some(journal) = &mut journal {
+                        journal.record_scan(&scan);
+                    }
+                    scan
+                }
+            };
+            let resolved = std::mem::take(&mut resume_state.resolved);
+            let check = check(&scan, &args.api, api.as_ref(), resolved, journal.as_mut()).await;
+            let mut manifest = Manifest::new(source, scan);
+            manifest.check = Some(check);
+            match journal {
+                Some(journal) => pack(
+                    &manifest,
+                    &args.selection,
+                    &args.output,
+                    journal,
+                    resume_state.archive,
+                ),
+                None => dry_run(&manifest, &args.selection, &args.output),
+            }
+            log::info!("Run complete");
+            log_scan_summary(&manifest.scan);
+        }
+    }
+
+    log::info!(
+        "Total runtime: {}s",
+        (Instant::now() - start_time).as_secs()
+    );
+}
+
+fn source_path(source: &SourceArgs) -> PathBuf {
+    match (&source.songs_folder, &source.lazer_dir) {
+        (Some(songs_folder), _) => songs_folder.clone(),
+        (None, Some(lazer_dir)) => lazer_dir.clone(),
+        (None, None) => unreachable!("clap requires a source"),
+    }
+}
 
-    let out_file = std::fs::File::create(args.output_tar_path.clone()).unwrap();
-    let mut tarrer = tar::Builder::new(out_file);
+fn archive_output(output: &OutputArgs) -> archive::Output {
+    match (&output.osz_dir, &output.output_dir, &output.output_tar_path) {
+        (Some(osz_dir), _, _) => archive::Output::OszDir(osz_dir.clone()),
+        (None, Some(output_dir), _) => archive::Output::Dir(output_dir.clone()),
+        (None, None, Some(tar_path)) => archive::Output::Tar(tar_path.clone()),
+        (None, None, None) => unreachable!("clap requires an output"),
+    }
+}
+
+/// How to compress tar output, exiting if the level doesn't suit the format. Cheap and
+/// silent, so it can be called up front to check the arguments and again when archiving.
+fn tar_compression(output: &OutputArgs) -> TarCompression {
+    let format = match (output.format, &output.output_tar_path) {
+        (Some(format), _) => format.into(),
+        (None, Some(tar_path)) => TarFormat::from_path(tar_path),
+        (None, None) => TarFormat::Plain,
+    };
+    if let Some(level) = output.compression_level {
+        match format.levels() {
+            Some(levels) if !levels.contains(&level) => {
+                log::error!("Compression level {level} isn't one of {levels:?} for {format:?}");
+                std::process::exit(1);
+            }
+            None => {
+                log::error!("The tar isn't compressed, pick a --format to use a compression level");
+                std::process::exit(1);
+            }
+            Some(_) => {}
+        }
+    }
+    TarCompression {
+        format,
+        level: output.compression_level,
+        workers: output
+            .compression_workers
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get() as u32)),
+    }
+}
+
+fn load_manifest(path: &Path) -> Manifest {
+    match Manifest::load(path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::error!("Couldn't read manifest {path:?}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn save_manifest(manifest: &Manifest, path: &Path) {
+    match manifest.save(path) {
+        Ok(()) => log::info!("Wrote manifest {path:?}"),
+        Err(e) => {
+            log::error!("Couldn't write manifest {path:?}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn open_journal(output: &OutputArgs, source: &Path) -> (Journal, journal::ResumeState) {
+    let journal_path = Journal::path_for(match &archive_output(output) {
+        archive::Output::Tar(path) | archive::Output::OszDir(path) | archive::Output::Dir(path) => {
+            path
+        }
+    });
+    if output.resume && journal_path.exists() {
+        match Journal::resume(&journal_path, source) {
+            Ok(resumed) => {
+                log::info!("Resuming the interrupted run recorded in {journal_path:?}");
+                resumed
+            }
+            Err(e) => {
+                log::error!("Can't resume from journal {journal_path:?}: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        if output.resume {
+            log::warn!("No journal at {journal_path:?} to resume from, starting over");
+        } else if journal_path.exists() {
+            log::warn!(
+                "Found the journal of an interrupted run at {journal_path:?}, starting over (pass --resume to continue it)"
+            );
+        }
+        match Journal::create(&journal_path, source) {
+            Ok(journal) => (journal, journal::ResumeState::default()),
+            Err(e) => {
+                log::error!("Couldn't create journal {journal_path:?}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
 
+/// `None` with `--offline`, nothing is fetched then.
+async fn authenticate(args: &ApiArgs, start_time: Instant) -> Option<OsuApiClient> {
+    if args.offline {
+        log::info!("Offline, the API won't be contacted");
+        return None;
+    }
     log::info!("Fetching access token");
-    let mut client = reqwest::Client::new();
-    let token = get_token(
-        &mut client,
-        reqwest::Url::from_str("https://osu.ppy.sh/oauth/token").expect("legit url"),
-        args.client_id,
-        &args.secret,
+    let Some(api) = OsuApiClient::authenticate(
+        args.client_id
+            .expect("clap requires a client id unless --offline"),
+        args.secret
+            .as_ref()
+            .expect("clap requires a secret unless --offline"),
+        RetryPolicy::new(args.max_attempts),
     )
-    .await;
+    .await
+    else {
+        log::error!("Couldn't obtain an access token, aborting");
+        std::process::exit(1);
+    };
 
     log::info!(
         "Obtained token after {}ms",
@@ -47,279 +274,507 @@ async fn main() {
         log::warn!(
             r#"Current rate limit is set at an insanely high 1200 requests per minute, with burst capability of up to 200 beyond that. If you require more, you probably fall into the above category of abuse. If you are doing more than 60 requests a minute, you should probably give peppy a yell."#
         );
-        log::warn!("The program will continue in 1 minute.");
-        sleep(Duration::from_secs(60));
     }
 
-    log::info!("Obtaining possible osu! song folders.");
+    Some(
+        api.beatmaps_url(args.lookup_beatmap_api_url.clone())
+            .beatmapsets_url(args.lookup_beatmapset_api_url.clone())
+            .rate_limit_per_minute(args.rate_limit_per_minute)
+            .burst(args.rate_limit_burst)
+            .max_concurrent_requests(args.max_concurrent_requests)
+            .unknown_retry_passes(args.unknown_retry_passes),
+    )
+}
+
+fn scan(source: &SourceArgs) -> Scan {
+    if let Some(lazer_dir) = &source.lazer_dir {
+        let realm_export = source
+            .lazer_realm_export
+            .as_ref()
+            .expect("clap requires an export with --lazer-dir");
+        return match LazerStore::open(lazer_dir, realm_export) {
+            Ok(store) => store.scan(),
+            Err(e) => {
+                log::error!("Couldn't read lazer realm export {realm_export:?}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
     let section_start_time = Instant::now();
-    let song_dirs = paths_in_dir_meta_filter(&args.songs_folder, |meta| meta.is_dir());
-    let song_dirs_count = song_dirs.len();
-    log::info!("Possible song dirs found: {}", song_dirs_count);
+    let songs_folder = source_path(source);
+    let mut scanner = SongsScanner::new(&songs_folder);
+    if let Some(workers) = source.scan_workers {
+        scanner = scanner.workers(workers);
+    }
+    if let Some(osu_db_path) = &source.osu_db {
+        match OsuDb::open(osu_db_path) {
+            Ok(db) => {
+                log::info!(
+                    "Read {} difficulties from {osu_db_path:?}",
+                    db.beatmaps.len()
+                );
+                scanner = scanner.with_osu_db(db);
+            }
+            Err(e) => {
+                log::warn!("Couldn't read {osu_db_path:?}, reading every .osu: {e}")
+            }
+        }
+    }
+    let scan = match scanner.scan() {
+        Ok(scan) => scan,
+        Err(e) => {
+            log::error!("Couldn't read songs folder {songs_folder:?}: {e}");
+            std::process::exit(1);
+        }
+    };
     log::info!(
-        "Song dirs collected after {}ms ({}ms total runtime)",
+        "Songs folder scanned after {}ms",
         (Instant::now() - section_start_time).as_millis(),
-        (Instant::now() - start_time).as_millis()
     );
+    scan
+}
+
+/// Asks the API about every ID the scan couldn't settle locally. IDs in `resolved` were
+/// answered before an interruption and aren't asked again. Without `api` only the status
+/// cache and osu!.db are consulted, and whatever they leave open is marked unverified.
+async fn check(
+    scan: &Scan,
+    args: &ApiArgs,
+    api: Option<&OsuApiClient>,
+    resolved: HashMap<u64, SubmissionStatus>,
+    mut journal: Option<&mut Journal>,
+) -> CheckResults {
     let section_start_time = Instant::now();
+    let mut classifier = SubmissionClassifier::new(scan.song_folders.iter().cloned());
+    let ids_to_check_count = classifier.ids_to_check().len();
 
-    log::info!("Fetching list of files in song folders.");
-    let files_in_song_dirs: Vec<PathBuf> = song_dirs
+    let cache_path = if args.no_cache {
+        None
+    } else {
+        args.cache_path
+            .clone()
+            .or_else(cache::StatusCache::default_path)
+    };
+    let mut status_cache = cache_path
+        .as_deref()
+        .map(cache::StatusCache::load)
+        .unwrap_or_default();
+    let now = SystemTime::now();
+    let mut statuses: HashMap<u64, SubmissionStatus> = resolved
         .into_iter()
-        .map(|song_dir| paths_in_dir_meta_filter(&song_dir, |meta| meta.is_file()))
-        .flatten()
+        .filter(|(id, _)| classifier.ids_to_check().contains_key(id))
+        .collect();
+    let resumed_count = statuses.len();
+    if resumed_count > 0 {
+        log::info!("{resumed_count} IDs already answered before the interruption");
+    }
+    let unanswered: Vec<u64> = classifier
+        .ids_to_check()
+        .keys()
+        .filter(|id| !statuses.contains_key(*id))
+        .copied()
         .collect();
-    let files_in_song_dirs_count = files_in_song_dirs.len();
-    log::info!("Files found: {}", files_in_song_dirs_count);
+    for id in unanswered {
+        if let Some(status) = status_cache.get(id, || classifier.local_md5s(id), now) {
+            statuses.insert(id, status);
+        }
+    }
     log::info!(
-        "Files found after {}ms ({}ms total runtime)",
-        (Instant::now() - section_start_time).as_millis(),
-        (Instant::now() - start_time).as_millis()
+        "{} of {ids_to_check_count} IDs answered from the status cache",
+        statuses.len() - resumed_count,
     );
-    let section_start_time = Instant::now();
 
-    log::info!("Filtering to .osu files.");
-    let dot_osu_file_paths: Vec<PathBuf> = files_in_song_dirs
-        .into_iter()
-        .filter(|p| {
-            if let Some(ext) = p.extension() {
-                ext == "osu"
-            } else {
-                false
+    let Some(api) = api else {
+        let from_db = classifier.statuses_from_db(&statuses);
+        log::info!("{} IDs osu!.db saw as unsubmitted", from_db.len());
+        statuses.extend(from_db);
+        classifier.apply_statuses(&statuses);
+        let unverified = classifier.settle_offline(&statuses);
+        log::info!(
+            "{} of {ids_to_check_count} IDs couldn't be verified offline",
+            unverified.len()
+        );
+        return CheckResults {
+            statuses,
+            offline: true,
+            unverified,
+            ..Default::default()
+        };
+    };
+    let set_ids = classifier.sets_to_look_up(&statuses);
+    log::info!("Beginning API requests for {} beatmap sets.", set_ids.len());
+    let sets = api.lookup_beatmapsets(&set_ids).await;
+    let missing_set_folders = classifier.mark_missing_sets(&sets);
+    let mut looked_up = classifier.statuses_from_sets(&sets);
+    if let Some(journal) = journal.as_deref_mut() {
+        journal.record_resolved(&looked_up);
+    }
+    log::info!("{} IDs answered by their set", looked_up.len());
+
+    // IDs a set didn't list may belong to another set, those are asked about one by one.
+    let ids: Vec<u64> = classifier
+        .ids_to_check()
+        .keys()
+        .filter(|id| !statuses.contains_key(*id) && !looked_up.contains_key(*id))
+        .copied()
+        .collect();
+    log::info!("Beginning API requests for {} .osu files.", ids.len());
+    let looked_up_by_id = api
+        .lookup_submission_statuses(&ids, |chunk| {
+            if let Some(journal) = journal.as_deref_mut() {
+                journal.record_resolved(chunk);
             }
         })
-        .collect();
-    log::info!(".osu files found: {}", dot_osu_file_paths.len());
-    log::info!(
-        ".osu files found after {}ms ({}ms total runtime)",
-        (Instant::now() - section_start_time).as_millis(),
-        (Instant::now() - start_time).as_millis()
-    );
-    let section_start_time = Instant::now();
-
-    let mut ids_to_containing_folders_to_check: HashMap<u64, PathBuf> = HashMap::new();
-    let mut song_folders_with_unsubmitted = HashSet::new();
+        .await;
+    looked_up.extend(looked_up_by_id);
 
-    for dot_osu_file_path in &dot_osu_file_paths {
-        let contents = read_to_string(&dot_osu_file_path);
-        if let Err(e) = &contents {
-            log::error!("Failed reading file {dot_osu_file_path:?}: {e:?}");
-            continue;
+    if let Some(cache_path) = &cache_path {
+        let now = SystemTime::now();
+        for (id, status) in &looked_up {
+            status_cache.insert(*id, || classifier.local_md5s(*id), status, now);
         }
-        let contents = contents.expect("Checked");
-        let id: Option<u64> = contents.lines().find_map(|l| {
-            if l.starts_with("BeatmapID:") {
-                if let Some((_, id)) = l.split_once(':') {
-                    let id = id.parse::<u64>().ok();
-                    if let Some(id) = id {
-                        if id == 0 {
-                            log::info!("Found unsubmitted: {dot_osu_file_path:#?}");
-                            song_folders_with_unsubmitted
-                                .insert(dot_osu_file_path.parent().expect("checked").to_owned());
-                            None
-                        } else {
-                            Some(id)
-                        }
-                    } else {
-                        id
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        });
-        if let Some(id) = id {
-            ids_to_containing_folders_to_check
-                .insert(id, dot_osu_file_path.parent().expect("checked").to_owned());
+        match status_cache.save(cache_path) {
+            Ok(()) => log::info!(
+                "Saved {} entries to status cache {cache_path:?}",
+                status_cache.len()
+            ),
+            Err(e) => log::warn!("Couldn't save status cache {cache_path:?}: {e}"),
         }
     }
+    statuses.extend(looked_up);
 
-    log::info!(
-        "Beatmap IDs collected after {}ms ({}ms total runtime)",
-        (Instant::now() - section_start_time).as_millis(),
-        (Instant::now() - start_time).as_millis()
-    );
-    let section_start_time = Instant::now();
+    classifier.apply_statuses(&statuses);
 
     log::info!(
-        "Beginning API requests for {} .osu files.",
-        ids_to_containing_folders_to_check.len()
+        "API calls completed after {}ms",
+        (Instant::now() - section_start_time).as_millis(),
     );
-
-    let mut counter = 0;
-    let mut last_req_time = Instant::now();
-    let req_wait_time = Duration::from_millis(60000 / args.rate_limit_per_minute);
-    for chunk in &ids_to_containing_folders_to_check.iter().chunks(50) {
-        let chunk = chunk.collect_vec();
-        let next_req_time = last_req_time + req_wait_time;
-        let now = Instant::now();
-        if next_req_time > now {
-            sleep(next_req_time - now);
-        }
-        last_req_time = Instant::now();
-
-        log::info!(
-            "[{0}/{1}] Checking id {0} to {2}.",
-            counter * 50 + 1,
-            ids_to_containing_folders_to_check.len(),
-            chunk.len() + counter * 50,
-        );
-        let ids: Vec<u64> = chunk.iter().map(|(id, _)| **id).collect();
-        let map = check_song_ids_are_unsubmitted(
-            &ids,
-            &mut &mut client,
-            args.lookup_beatmap_api_url.clone(),
-            &token,
+    log::info!("Pulled API details for {ids_to_check_count} .osu files");
+    log::info!("Made {} API calls", api.requests_made());
+    let user = if args.login {
+        let login = Login::new(
+            args.client_id.expect("checked by authenticate"),
+            args.secret.as_ref().expect("checked by authenticate"),
+            RetryPolicy::new(args.max_attempts),
         )
-        .await;
-        for (id, path) in chunk.into_iter() {
-            if *map.get(id).unwrap_or(&true) {
-                log::info!("Unsubmitted found ({id}): {path:#?}");
-                song_folders_with_unsubmitted.insert(path.to_owned());
-            }
+        .redirect_port(args.redirect_port);
+        let user = login.user().await;
+        if user.is_none() {
+            log::warn!("Couldn't log in, your own maps won't be told apart");
         }
-        counter += 1;
+        user
+    } else {
+        None
+    };
+
+    CheckResults {
+        statuses,
+        missing_set_folders,
+        user,
+        ..Default::default()
     }
+}
 
-    log::info!("All song folders with unsubmitted .osu: {song_folders_with_unsubmitted:#?}");
-    log::info!(
-        "API calls completed after {}ms ({}ms total runtime)",
-        (Instant::now() - section_start_time).as_millis(),
-        (Instant::now() - start_time).as_millis()
-    );
+/// Archives what the manifest selects, continuing from `progress` if the output allows it.
+fn pack(
+    manifest: &Manifest,
+    selection: &SelectionArgs,
+    output: &OutputArgs,
+    mut journal: Journal,
+    mut progress: archive::Progress,
+) {
     let section_start_time = Instant::now();
+    let (archiver, folders_to_archive) = plan(manifest, selection, output);
+    log::info!("Archiving song folders");
+    let song_folders_to_archive_count = folders_to_archive.len();
+    let files_archived: usize = folders_to_archive.iter().map(|f| f.files.len()).sum();
 
-    log::info!("Tarring song folders");
-    let song_folders_with_unsubmitted_count = song_folders_with_unsubmitted.len();
-    for song_folder in song_folders_with_unsubmitted {
-        tarrer
-            .append_dir_all(song_folder.file_name().unwrap(), &song_folder)
-            .unwrap();
+    if !progress.is_empty() {
+        if archiver.can_resume(&folders_to_archive, &progress) {
+            log::info!(
+                "Continuing after the {} song folders already archived",
+                progress.archived.len()
+            );
+        } else {
+            log::warn!("The interrupted run's output can't be continued, rebuilding it");
+            progress = archive::Progress::default();
+        }
+    }
+    if progress.is_empty() {
+        journal.record_archive_started();
+    }
+    let summary = match archiver.write(&folders_to_archive, &progress, |folder, tar_offset| {
+        journal.record_archived(folder, tar_offset)
+    }) {
+        Ok(summary) => summary,
+        Err(e) => {
+            log::error!("Couldn't write the output, run again with --resume to continue: {e}");
+            std::process::exit(1);
+        }
+    };
+    match archiver.output() {
+        archive::Output::OszDir(osz_dir) => log::info!(
+            "Wrote {} .osz files to {:#?}",
+            summary.folders,
+            osz_dir.canonicalize().unwrap_or(osz_dir.clone())
+        ),
+        archive::Output::Dir(output_dir) => log::info!(
+            "Mirrored {} song folders into {:#?}, {} files were already up to date",
+            summary.folders,
+            output_dir.canonicalize().unwrap_or(output_dir.clone()),
+            summary.unchanged_files
+        ),
+        archive::Output::Tar(output_tar_path) if output.max_volume_size.is_some() => {
+            let index_path = archive::volume_index_path(output_tar_path);
+            log::info!(
+                "File tarring complete, {} volumes listed in {:#?}",
+                archiver
+                    .volumes(&folders_to_archive)
+                    .map_or(0, |volumes| volumes.len()),
+                index_path.canonicalize().unwrap_or(index_path.clone())
+            )
+        }
+        archive::Output::Tar(output_tar_path) => log::info!(
+            "File tarring complete, output path: {:#?}",
+            output_tar_path
+                .canonicalize()
+                .unwrap_or(output_tar_path.clone())
+        ),
+    }
+    if summary.deduplicated_files > 0 {
+        log::info!(
+            "Stored {} files identical to one already archived as links, saving {}",
+            summary.deduplicated_files,
+            format_size(summary.bytes_saved)
+        );
+    }
+    if let Err(e) = journal.finish() {
+        log::warn!("Couldn't remove finished journal: {e}");
     }
-    tarrer.finish().unwrap();
-    log::info!(
-        "File tarring complete, output path: {:#?}",
-        args.output_tar_path
-            .canonicalize()
-            .unwrap_or(args.output_tar_path)
-    );
     log::info!(
-        "File tarring completed after {}ms",
+        "File archiving completed after {}ms",
         (Instant::now() - section_start_time).as_millis(),
     );
-    log::info!("Run complete");
-    log::info!("Processed {song_dirs_count} song folders");
-    log::info!("Scanned {files_in_song_dirs_count} files in song dirs");
-    log::info!("Processed {} .osu files", dot_osu_file_paths.len());
-    log::info!(
-        "Pulled API details for {} .osu files",
-        ids_to_containing_folders_to_check.len()
-    );
-    log::info!("Made {counter} API calls");
-    log::info!("Tarred {song_folders_with_unsubmitted_count} song folders",);
+    if output.only_unsubmitted_difficulties {
+        log::info!(
+            "Archived {files_archived} files needed by the selected difficulties from {song_folders_to_archive_count} song folders"
+        );
+    } else {
+        log::info!("Archived {song_folders_to_archive_count} song folders");
+    }
+}
 
+/// Logs what `pack` would write and how big it would be, without writing anything.
+fn dry_run(manifest: &Manifest, selection: &SelectionArgs, output: &OutputArgs) {
+    let (archiver, folders_to_archive) = plan(manifest, selection, output);
+    let (sizes, total) = match archiver.projected_sizes(&folders_to_archive) {
+        Ok(projected) => projected,
+        Err(e) => {
+            log::error!("Couldn't size the selected files: {e}");
+            std::process::exit(1);
+        }
+    };
+    for (folder, size) in folders_to_archive.iter().zip(&sizes) {
+        log::info!(
+            "{:#?}: {} files, {}",
+            folder.folder,
+            folder.files.len(),
+            format_size(*size)
+        );
+    }
+    if output.max_volume_size.is_some() {
+        let volumes = archiver.volumes(&folders_to_archive).unwrap_or_default();
+        for volume in &volumes {
+            log::info!(
+                "{:#?}: {} song folders, {}",
+                volume.path,
+                volume.folders.len(),
+                format_size(archive::tar_size(&sizes[volume.folders.clone()]))
+            );
+        }
+        log::info!(
+            "Dry run: would split the output into {} volumes",
+            volumes.len()
+        );
+    }
     log::info!(
-        "Total runtime: {}s",
-        (Instant::now() - start_time).as_secs()
+        "Dry run: would archive {} song folders into {:#?}, {} in total{}",
+        folders_to_archive.len(),
+        match archiver.output() {
+            archive::Output::Tar(path)
+            | archive::Output::OszDir(path)
+            | archive::Output::Dir(path) => path,
+        },
+        format_size(total),
+        if archiver.exact_sizes() {
+            ""
+        } else {
+            " at most"
+        }
     );
 }
 
-async fn check_song_ids_are_unsubmitted(
-    ids: &[u64],
-    client: &mut reqwest::Client,
-    url: reqwest::Url,
-    token: &str,
-) -> HashMap<u64, bool> {
-    let query: Vec<(String, String)> = ids
-        .into_iter()
-        .map(|id| ("ids[]".to_owned(), id.to_string()))
-        .collect();
+/// Classifies the manifest and works out the files to write for the selection.
+fn plan(
+    manifest: &Manifest,
+    selection: &SelectionArgs,
+    output: &OutputArgs,
+) -> (Archiver, Vec<archive::FolderToArchive>) {
+    let (song_folders, classification) = manifest.classify();
+    log_classification(&classification, selection);
+    write_report(manifest, selection);
 
-    let res = client
-        .get(url)
-        .query(&query)
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .header(reqwest::header::ACCEPT, "application/json")
-        .header(reqwest::header::AUTHORIZATION, format!("Bearer {token}"))
-        .send()
-        .await;
+    let archiver = Archiver::new(archive_output(output))
+        .only_selected_difficulties(output.only_unsubmitted_difficulties)
+        .deduplicate(!output.no_dedup)
+        .compression(tar_compression(output))
+        .max_volume_size(output.max_volume_size)
+        .mirror_strategy(output.mirror.map_or_else(Default::default, Into::into));
+    let folders_to_archive =
+        archiver.folders_to_archive(select(manifest, classification, selection), &song_folders);
+    (archiver, folders_to_archive)
+}
 
-    let mut out: HashMap<u64, bool> = HashMap::new();
-    if let Ok(res) = res {
-        if let Ok(text) = res.text().await {
-            if let Ok(beatmaps_response) =
-                serde_json::from_str::<spec::web::GetBeatmapsResponse>(&text)
-            {
-                for beatmap in beatmaps_response.beatmaps {
-                    log::info!(
-                        "Submitted found: {} - {}[{}]",
-                        beatmap.beatmapset.artist,
-                        beatmap.beatmapset.title,
-                        beatmap.version
-                    );
-                    out.insert(beatmap.id, false);
-                }
-            } else {
-                log::error!("Unexpected response format, aborting: {text}");
-            }
-        } else {
-            log::error!("Network error getting body");
+/// What the selection flags pick, with how much of it is the logged-in user's own.
+fn select(
+    manifest: &Manifest,
+    classification: Classification,
+    selection: &SelectionArgs,
+) -> FolderSelection {
+    let Some(user) = manifest.user() else {
+        if selection.only_mine {
+            log::error!("Nobody logged in when this was checked, check again with --login to use --only-mine");
+            std::process::exit(1);
         }
+        return manifest.select(classification, selection.selection());
+    };
+    let selected = manifest.select(classification, selection.selection());
+    let own_count = manifest
+        .scan
+        .song_folders
+        .iter()
+        .filter(|folder| {
+            selected.get(&folder.path).is_some_and(|paths| {
+                folder.difficulties.iter().any(|d| {
+                    paths.contains(&d.path) && manifest.is_own(&d.metadata.creator) == Some(true)
+                })
+            })
+        })
+        .count();
+    log::info!(
+        "{own_count} of the {} selected song folders have difficulties by {}",
+        selected.len(),
+        user.username
+    );
+    selected
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
     } else {
-        log::error!("Network error sending request");
+        format!("{size:.1} {}", UNITS[unit])
     }
-    out
 }
 
-async fn get_token(
-    client: &mut reqwest::Client,
-    auth_url: reqwest::Url,
-    client_id: u64,
-    secret: &SecretString,
-) -> String {
-    let body =
-        serde_json::to_string(&spec::web::TokenRequest::new(client_id, &secret)).expect("encodes");
-    let res = client
-        .post(auth_url)
-        .body(body)
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .header(reqwest::header::ACCEPT, "application/json")
-        .send()
-        .await
-        .expect("auth request should succeed");
-    let text = res.text().await.expect("auth response should succeed");
-    let res: spec::web::TokenResponse =
-        serde_json::from_str(&text).expect("should get creds, check secret/clientID");
-    res.access_token
+fn write_report(manifest: &Manifest, selection: &SelectionArgs) {
+    let Some(path) = &selection.report_json else {
+        return;
+    };
+    match manifest.report(selection.selection()).save(path) {
+        Ok(()) => log::info!("Wrote report {path:?}"),
+        Err(e) => log::warn!("Couldn't write report {path:?}: {e}"),
+    }
 }
 
-fn paths_in_dir_meta_filter<F>(dir: &Path, meta_check: F) -> Vec<PathBuf>
-where
-    F: Fn(&Metadata) -> bool,
-{
-    let mut res_paths = Vec::new();
-    if let Ok(paths) = std::fs::read_dir(dir) {
-        for p in paths {
-            // CBA with if let indent spam.
-            if p.is_err() {
-                continue;
-            }
-            let p = p.expect("checked");
+fn log_scan_summary(scan: &Scan) {
+    log::info!("Processed {} song folders", scan.song_dirs_count);
+    log::info!("Scanned {} files in song dirs", scan.files_count);
+    log::info!("Processed {} .osu files", scan.osu_files_count);
+}
 
-            let meta = p.metadata();
-            if meta.is_err() {
-                continue;
-            }
-            let meta = meta.expect("checked");
+fn log_classification(classification: &Classification, selection: &SelectionArgs) {
+    log::info!(
+        "All song folders with unsubmitted .osu: {:#?}",
+        classification.unsubmitted.keys()
+    );
+    log::info!(
+        "All song folders with .osu modified since upload: {:#?}",
+        classification.modified.keys()
+    );
+    log::info!(
+        "All song folders mixing multiple sets: {:#?}",
+        classification.mixed_sets
+    );
+    log::info!(
+        "All beatmap IDs claimed by more than one .osu: {:#?}",
+        classification.duplicate_ids
+    );
+    log::info!(
+        "All song folders with .osu of unknown status: {:#?}",
+        classification.unknown.keys()
+    );
+    log::info!(
+        "All song folders with .osu unverified offline: {:#?}",
+        classification.unverified.keys()
+    );
 
-            if meta_check(&meta) {
-                res_paths.push(p.path());
-            }
+    let song_folders_modified_only_count = classification
+        .modified
+        .keys()
+        .filter(|folder| !classification.unsubmitted.contains_key(*folder))
+        .count();
+    let song_folders_unknown_only_count = classification
+        .unknown
+        .keys()
+        .filter(|folder| !classification.unsubmitted.contains_key(*folder))
+        .count();
+    let song_folders_unverified_only_count = classification
+        .unverified
+        .keys()
+        .filter(|folder| !classification.unsubmitted.contains_key(*folder))
+        .count();
+    log::info!(
+        "Found {} song folders with unsubmitted .osu",
+        classification.unsubmitted.len()
+    );
+    log::info!(
+        "Found {song_folders_modified_only_count} further song folders with .osu modified since upload{}",
+        if selection.include_modified {
+            ""
+        } else {
+            " (skipped, pass --include-modified to extract them)"
         }
-        res_paths
-    } else {
-        panic!("Failed to read songs dir: {dir:?}");
-    }
+    );
+    log::info!(
+        "Found {song_folders_unknown_only_count} further song folders with .osu the API couldn't answer for{}",
+        if selection.include_unknown {
+            ""
+        } else {
+            " (skipped, pass --include-unknown to extract them)"
+        }
+    );
+    log::info!(
+        "Found {song_folders_unverified_only_count} further song folders with .osu that couldn't be verified offline{}",
+        if selection.include_unverified {
+            ""
+        } else {
+            " (skipped, pass --include-unverified to extract them)"
+        }
+    );
+    log::info!(
+        "Found {} song folders mixing multiple sets",
+        classification.mixed_sets.len()
+    );
+    log::info!(
+        "Found {} beatmap IDs claimed by more than one .osu, each copy was classified on its own",
+        classification.duplicate_ids.len()
+    );
 }
@@ -0,0 +1,510 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    classify::{Classification, FolderSelection, Selection, SubmissionClassifier},
+    osu_db::RankedStatus,
+    osu_file::Metadata,
+    song_folder::{Scan, SetClassification, SongFolder},
+    spec::web::User,
+    submission::SubmissionStatus,
+};
+
+/// Bumped whenever the layout of [`Manifest`] or [`Report`] changes incompatibly.
+pub const VERSION: u32 = 1;
+
+/// What one stage of a run hands to the next: the scan, and once checked, the API's answers.
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// The Songs folder or lazer data directory scanned.
+    pub source: PathBuf,
+    pub scan: Scan,
+    /// `None` until the manifest has been checked against the API.
+    pub check: Option<CheckResults>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct CheckResults {
+    pub statuses: HashMap<u64, SubmissionStatus>,
+    /// Folders whose claimed set turned out not to exist.
+    pub missing_set_folders: Vec<PathBuf>,
+    /// Who was logged in during the check, if anyone.
+    #[serde(default)]
+    pub user: Option<User>,
+    /// Whether the check was made with `--offline`, from local data and the status cache only.
+    #[serde(default)]
+    pub offline: bool,
+    /// IDs an offline check had no cached answer or osu!.db status for.
+    #[serde(default)]
+    pub unverified: Vec<u64>,
+}
+
+/// Every scanned folder and difficulty with what was decided about it, for scripts to read
+/// instead of the log.
+#[derive(Serialize, Deserialize)]
+pub struct Report {
+    pub version: u32,
+    pub source: PathBuf,
+    /// Whether the API was asked. If not, only the local markers are reflected.
+    pub checked: bool,
+    /// Whether the check was offline, so only the local data and status cache are reflected.
+    pub offline: bool,
+    /// Sorted by path.
+    pub folders: Vec<FolderReport>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FolderReport {
+    pub path: PathBuf,
+    /// The uploaded set all difficulties agree on, if any.
+    pub beatmap_set_id: Option<u64>,
+    /// Difficulties from more than one uploaded set.
+    pub mixed_sets: bool,
+    /// Whether anything in the folder gets archived.
+    pub included: bool,
+    /// Sorted by path.
+    pub difficulties: Vec<DifficultyReport>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DifficultyReport {
+    pub path: PathBuf,
+    pub metadata: Metadata,
+    /// `None` if neither the scan nor the check had a reason to hash it.
+    pub md5: Option<String>,
+    pub beatmap_id: Option<i64>,
+    pub beatmap_set_id: Option<i64>,
+    /// The API's answer for `beatmap_id`, if it was asked.
+    pub api_status: Option<SubmissionStatus>,
+    /// What osu!.db last heard about this file, if it was read from there.
+    pub db_ranked_status: Option<RankedStatus>,
+    /// Whether the `Creator:` is the logged-in user. `None` if nobody logged in.
+    pub own: Option<bool>,
+    /// Other .osu files claiming the same `beatmap_id`, classified separately from this one.
+    pub duplicates: Vec<PathBuf>,
+    pub included: bool,
+    pub reason: Reason,
+}
+
+/// Why a difficulty was or wasn't included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// The .osu itself says it was never uploaded.
+    MarkedUnsubmitted,
+    /// The API doesn't know its beatmap ID.
+    NotFound,
+    /// The set its folder claims doesn't exist.
+    SetMissing,
+    /// Uploaded, but the local .osu no longer matches.
+    Modified,
+    /// The API couldn't answer for it.
+    Unknown,
+    /// Checked offline with nothing local to confirm it either way.
+    Unverified,
+    /// Uploaded and unchanged.
+    Submitted,
+    /// Has an ID the API hasn't been asked about yet.
+    NotChecked,
+    /// No beatmap ID to ask about, e.g. from an old format version.
+    NoId,
+}
+
+impl Manifest {
+    pub fn new(source: PathBuf, scan: Scan) -> Self {
+        Self {
+            version: VERSION,
+            source,
+            scan,
+            check: None,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let manifest: Self = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if manifest.version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "manifest version {} isn't the supported {VERSION}, scan again",
+                    manifest.version
+                ),
+            ));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_json(path, self)
+    }
+
+    /// Replays the recorded answers through a classifier, or just the local classification if
+    /// the manifest hasn't been checked yet.
+    pub fn classify(&self) -> (HashMap<PathBuf, SongFolder>, Classification) {
+        let mut classifier = SubmissionClassifier::new(self.scan.song_folders.iter().cloned());
+        if let Some(check) = &self.check {
+            classifier.apply_statuses(&check.statuses);
+            for folder in &check.missing_set_folders {
+                classifier.mark_set_missing(folder);
+            }
+            classifier.mark_unverified(&check.unverified);
+        }
+        classifier.into_parts()
+    }
+
+    pub fn user(&self) -> Option<&User> {
+        self.check.as_ref()?.user.as_ref()
+    }
+
+    /// Whether `creator` is the logged-in user, under their current or a previous name.
+    /// `None` if nobody logged in.
+    pub fn is_own(&self, creator: &str) -> Option<bool> {
+        let user = self.user()?;
+        let creator = creator.trim();
+        Some(
+            std::iter::once(&user.username)
+                .chain(&user.previous_usernames)
+                .any(|name| name.eq_ignore_ascii_case(creator)),
+        )
+    }
+
+    /// What `classification` selects, narrowed down to the logged-in user's own difficulties
+    /// if asked for. Folders left without any are dropped.
+    pub fn select(&self, classification: Classification, selection: Selection) -> FolderSelection {
+        let mut selected = classification.select(selection);
+        if selection.only_own {
+            let creators: HashMap<&Path, &str> = self
+                .scan
+                .song_folders
+                .iter()
+                .flat_map(|folder| &folder.difficulties)
+                .map(|d| (d.path.as_path(), d.metadata.creator.as_str()))
+                .collect();
+            for difficulties in selected.values_mut() {
+                difficulties.retain(|path| {
+                    creators
+                        .get(path.as_path())
+                        .is_some_and(|creator| self.is_own(creator) == Some(true))
+                });
+            }
+            selected.retain(|_, difficulties| !difficulties.is_empty());
+        }
+        selected
+    }
+
+    pub fn report(&self, selection: Selection) -> Report {
+        // Holds the MD5s the check hashed, the scan only has the ones osu!.db recorded.
+        let (classified_folders, classification) = self.classify();
+        let md5s: HashMap<&Path, &String> = classified_folders
+            .values()
+            .flat_map(|folder| &folder.difficulties)
+            .filter_map(|d| Some((d.path.as_path(), d.md5.as_ref()?)))
+            .collect();
+        let missing_set_folders: HashSet<&Path> = self
+            .check
+            .iter()
+            .flat_map(|check| check.missing_set_folders.iter().map(PathBuf::as_path))
+            .collect();
+        let in_class = |class: &HashMap<PathBuf, HashSet<PathBuf>>, folder: &Path, path: &Path| {
+            class.get(folder).is_some_and(|paths| paths.contains(path))
+        };
+
+        let mut folders: Vec<FolderReport> = self
+            .scan
+            .song_folders
+            .iter()
+            .map(|song_folder| {
+                let folder = song_folder.path.as_path();
+                let set_classification = song_folder.set_classification();
+                let mut difficulties: Vec<DifficultyReport> = song_folder
+                    .difficulties
+                    .iter()
+                    .map(|difficulty| {
+                        let path = difficulty.path.as_path();
+                        let beatmap_id = difficulty.metadata.beatmap_id;
+                        let api_status = self.check.as_ref().and_then(|check| {
+                            let id = u64::try_from(beatmap_id?).ok()?;
+                            check.statuses.get(&id).cloned()
+                        });
+                        let own = self.is_own(&difficulty.metadata.creator);
+                        let duplicates = beatmap_id
+                            .and_then(|id| u64::try_from(id).ok())
+                            .and_then(|id| classification.duplicate_ids.get(&id))
+                            .map(|paths| paths.iter().filter(|p| *p != path).cloned().collect())
+                            .unwrap_or_default();
+                        let (reason, included) =
+                            if in_class(&classification.unsubmitted, folder, path) {
+                                let reason = if set_classification == SetClassification::Unsubmitted
+                                    || beatmap_id == Some(0)
+                                {
+                                    Reason::MarkedUnsubmitted
+                                } else if missing_set_folders.contains(folder) {
+                                    Reason::SetMissing
+                                } else {
+                                    Reason::NotFound
+                                };
+                                (reason, true)
+                            } else if in_class(&classification.modified, folder, path) {
+                                (Reason::Modified, selection.include_modified)
+                            } else if in_class(&classification.unknown, folder, path) {
+                                (Reason::Unknown, selection.include_unknown)
+                            } else if in_class(&classification.unverified, folder, path) {
+                                (Reason::Unverified, selection.include_unverified)
+                            } else if api_status.is_some()
+                                || difficulty
+                                    .ranked_status
+                                    .is_some_and(RankedStatus::is_submitted)
+                            {
+                                (Reason::Submitted, false)
+                            } else if beatmap_id.is_some_and(|id| id > 0) {
+                                (Reason::NotChecked, false)
+                            } else {
+                                (Reason::NoId, false)
+                            };
+                        DifficultyReport {
+                            path: difficulty.path.clone(),
+                            metadata: difficulty.metadata.clone(),
+                            md5: md5s.get(path).map(|md5| (*md5).clone()),
+                            beatmap_id,
+                            beatmap_set_id: difficulty.metadata.beatmap_set_id,
+                            api_status,
+                            db_ranked_status: difficulty.ranked_status,
+                            own,
+                            duplicates,
+                            included: included && (!selection.only_own || own == Some(true)),
+                            reason,
+                        }
+                    })
+                    .collect();
+                difficulties.sort_by(|a, b| a.path.cmp(&b.path));
+                FolderReport {
+                    path: song_folder.path.clone(),
+                    beatmap_set_id: song_folder.set_id(),
+                    mixed_sets: classification.mixed_sets.contains(folder),
+                    included: difficulties.iter().any(|d| d.included),
+                    difficulties,
+                }
+            })
+            .collect();
+        folders.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Report {
+            version: VERSION,
+            source: self.source.clone(),
+            checked: self.check.is_some(),
+            offline: self.check.as_ref().is_some_and(|check| check.offline),
+            folders,
+        }
+    }
+}
+
+impl Report {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_json(path, self)
+    }
+}
+
+/// Writes to a temporary file first so an interrupted save can't lose what was there.
+fn write_json(path: &Path, value: &impl Serialize) -> io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    serde_json::to_writer_pretty(BufWriter::new(File::create(&tmp_path)?), value)?;
+    std::fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::song_folder::LocalDifficulty;
+
+    fn difficulty(folder: &str, name: &str, beatmap_id: Option<i64>) -> LocalDifficulty {
+        LocalDifficulty {
+            path: PathBuf::from(folder).join(name),
+            metadata: Metadata {
+                beatmap_id,
+                beatmap_set_id: Some(10),
+                ..Default::default()
+            },
+            md5: Some("local".to_owned()),
+            ranked_status: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let scan = Scan {
+            song_dirs_count: 1,
+            files_count: 4,
+            osu_files_count: 4,
+            song_folders: vec![SongFolder {
+                path: PathBuf::from("song"),
+                difficulties: vec![
+                    difficulty("song", "d.osu", None),
+                    difficulty("song", "c.osu", Some(3)),
+                    difficulty("song", "b.osu", Some(2)),
+                    difficulty("song", "a.osu", Some(0)),
+                ],
+                files: None,
+            }],
+        };
+        let mut manifest = Manifest::new(PathBuf::from("Songs"), scan);
+        let reasons = |report: Report| -> Vec<(Reason, bool)> {
+            report.folders[0]
+                .difficulties
+                .iter()
+                .map(|d| (d.reason, d.included))
+                .collect()
+        };
+
+        let unchecked = manifest.report(Selection::default());
+        assert!(!unchecked.checked);
+        assert_eq!(
+            reasons(unchecked),
+            vec![
+                (Reason::MarkedUnsubmitted, true),
+                (Reason::NotChecked, false),
+                (Reason::NotChecked, false),
+                (Reason::NoId, false),
+            ]
+        );
+
+        manifest.check = Some(CheckResults {
+            statuses: HashMap::from([
+                (2, SubmissionStatus::NotFound),
+                (3, SubmissionStatus::Unknown),
+            ]),
+            missing_set_folders: Vec::new(),
+            user: None,
+            offline: false,
+            unverified: Vec::new(),
+        });
+        let selection = Selection {
+            include_unknown: true,
+            ..Default::default()
+        };
+        let report = manifest.report(selection);
+        assert!(report.folders[0].included);
+        assert!(matches!(
+            report.folders[0].difficulties[1].api_status,
+            Some(SubmissionStatus::NotFound)
+        ));
+        assert_eq!(
+            reasons(report),
+            vec![
+                (Reason::MarkedUnsubmitted, true),
+                (Reason::NotFound, true),
+                (Reason::Unknown, true),
+                (Reason::NoId, false),
+            ]
+        );
+
+        manifest.scan.song_folders[0].difficulties[3]
+            .metadata
+            .creator = "oldme ".to_owned();
+        manifest.check.as_mut().unwrap().user = Some(User {
+            id: 1,
+            username: "Me".to_owned(),
+            previous_usernames: vec!["OldMe".to_owned()],
+        });
+        let selection = Selection {
+            only_own: true,
+            ..selection
+        };
+        let (_, classification) = manifest.classify();
+        assert_eq!(
+            manifest.select(classification, selection),
+            FolderSelection::from([(
+                PathBuf::from("song"),
+                HashSet::from([PathBuf::from("song/a.osu")])
+            )])
+        );
+        let report = manifest.report(selection);
+        assert_eq!(report.folders[0].difficulties[0].own, Some(true));
+        assert_eq!(
+            reasons(report),
+            vec![
+                (Reason::MarkedUnsubmitted, true),
+                (Reason::NotFound, false),
+                (Reason::Unknown, false),
+                (Reason::NoId, false),
+            ]
+        );
+
+        manifest.check = Some(CheckResults {
+            statuses: HashMap::from([(2, SubmissionStatus::NotFound)]),
+            offline: true,
+            unverified: vec![3],
+            ..Default::default()
+        });
+        let report = manifest.report(Selection {
+            include_unverified: true,
+            ..Default::default()
+        });
+        assert!(report.offline);
+        assert_eq!(
+            reasons(report),
+            vec![
+                (Reason::MarkedUnsubmitted, true),
+                (Reason::NotFound, true),
+                (Reason::Unverified, true),
+                (Reason::NoId, false),
+            ]
+        );
+    }
+
+    fn manifest() -> Manifest {
+        let mut manifest = Manifest::new(
+            PathBuf::from("Songs"),
+            Scan {
+                song_dirs_count: 1,
+                files_count: 2,
+                osu_files_count: 1,
+                song_folders: Vec::new(),
+            },
+        );
+        manifest.check = Some(CheckResults {
+            statuses: HashMap::from([(1, SubmissionStatus::NotFound)]),
+            missing_set_folders: vec![PathBuf::from("Songs/2 a - b")],
+            ..Default::default()
+        });
+        manifest
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("manifest-test-{}.json", std::process::id()));
+        let manifest = manifest();
+        manifest.save(&path).unwrap();
+        let loaded = Manifest::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(loaded.unwrap()).unwrap(),
+            serde_json::to_value(manifest).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_load_version_mismatch() {
+        let path =
+            std::env::temp_dir().join(format!("manifest-version-test-{}.json", std::process::id()));
+        let mut value = serde_json::to_value(manifest()).unwrap();
+        value["version"] = 0.into();
+        std::fs::write(&path, value.to_string()).unwrap();
+        let old = Manifest::load(&path);
+        value.as_object_mut().unwrap().remove("version");
+        std::fs::write(&path, value.to_string()).unwrap();
+        let unversioned = Manifest::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(old.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        assert!(unversioned.is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! Reader for osu!stable's `osu!.db`, which lists every difficulty the client knows about.
+//!
+//! Format reference: https://github.com/ppy/osu/wiki/Legacy-database-file-structure
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::osu_file::Metadata;
+
+/// Star ratings became floats and difficulty settings singles in this version.
+const FLOAT_DIFFICULTY_VERSION: u32 = 20140609;
+/// Entries stopped being prefixed by their size in this version.
+const NO_ENTRY_SIZE_VERSION: u32 = 20191106;
+/// Star rating pairs switched from doubles to singles in this version.
+const SINGLE_STAR_RATING_VERSION: u32 = 20250107;
+/// .NET ticks (100ns since 0001-01-01) at the Unix epoch.
+const UNIX_EPOCH_TICKS: u64 = 621_355_968_000_000_000;
+
+#[derive(Debug, Clone)]
+pub struct OsuDb {
+    pub version: u32,
+    pub folder_count: u32,
+    pub player_name: Option<String>,
+    pub beatmaps: Vec<DbBeatmap>,
+}
+
+/// One difficulty as osu! last saw it.
+#[derive(Debug, Clone, Default)]
+pub struct DbBeatmap {
+    pub artist: String,
+    pub artist_unicode: String,
+    pub title: String,
+    pub title_unicode: String,
+    pub creator: String,
+    pub version: String,
+    pub audio_file_name: String,
+    pub md5: String,
+    pub osu_file_name: String,
+    pub ranked_status: RankedStatus,
+    /// `0` for difficulties that have never been uploaded.
+    pub beatmap_id: i32,
+    /// `-1` for sets that have never been uploaded.
+    pub beatmap_set_id: i32,
+    pub source: String,
+    pub tags: String,
+    /// Relative to the Songs folder.
+    pub folder_name: String,
+    /// When the .osu was last modified as osu! saw it, `None` if it didn't record it.
+    pub last_modified: Option<SystemTime>,
+}
+
+/// The ranked status byte, as of the last time osu! checked online.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankedStatus {
+    #[default]
+    Unknown,
+    Unsubmitted,
+    /// osu! lumps these three together.
+    PendingWipGraveyard,
+    Ranked,
+    Approved,
+    Qualified,
+    Loved,
+    Other(u8),
+}
+
+impl RankedStatus {
+    /// Whether osu! found this exact file online, so it's uploaded and unchanged since.
+    pub fn is_submitted(self) -> bool {
+        matches!(
+            self,
+            RankedStatus::PendingWipGraveyard
+                | RankedStatus::Ranked
+                | RankedStatus::Approved
+                | RankedStatus::Qualified
+                | RankedStatus::Loved
+        )
+    }
+}
+
+impl From<u8> for RankedStatus {
+    fn from(byte: u8) -> Self {
+        match byte {
+            0 => RankedStatus::Unknown,
+            1 => RankedStatus::Unsubmitted,
+            2 => RankedStatus::PendingWipGraveyard,
+            4 => RankedStatus::Ranked,
+            5 => RankedStatus::Approved,
+            6 => RankedStatus::Qualified,
+            7 => RankedStatus::Loved,
+            other => RankedStatus::Other(other),
+        }
+    }
+}
+
+impl OsuDb {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(reader: impl Read) -> io::Result<Self> {
+        let mut reader = DbReader(reader);
+        let version = reader.u32()?;
+        let folder_count = reader.u32()?;
+        let _account_unlocked = reader.bool()?;
+        let _unlock_date = reader.u64()?;
+        let player_name = reader.string()?;
+        let beatmap_count = reader.u32()?;
+        let mut beatmaps = Vec::with_capacity(beatmap_count.min(1 << 16) as usize);
+        for _ in 0..beatmap_count {
+            beatmaps.push(reader.beatmap(version)?);
+        }
+        Ok(Self {
+            version,
+            folder_count,
+            player_name,
+            beatmaps,
+        })
+    }
+}
+
+impl DbBeatmap {
+    /// The same fields the `[Metadata]` section of the .osu would give.
+    pub fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            title_unicode: self.title_unicode.clone(),
+            artist: self.artist.clone(),
+            artist_unicode: self.artist_unicode.clone(),
+            creator: self.creator.clone(),
+            version: self.version.clone(),
+            source: self.source.clone(),
+            tags: self.tags.split_whitespace().map(str::to_owned).collect(),
+            beatmap_id: Some(self.beatmap_id.into()),
+            beatmap_set_id: Some(self.beatmap_set_id.into()),
+        }
+    }
+}
+
+struct DbReader<R>(R);
+
+impl<R: Read> DbReader<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.0.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn skip(&mut self, len: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.0).take(len), &mut io::sink())?;
+        if skipped < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    /// A .NET `DateTime` in ticks. `None` for zero, anything before the Unix epoch and
+    /// anything too far after it to count in nanoseconds.
+    fn date_time(&mut self) -> io::Result<Option<SystemTime>> {
+        let nanos = self
+            .u64()?
+            .checked_sub(UNIX_EPOCH_TICKS)
+            .filter(|ticks| *ticks > 0)
+            .and_then(|ticks| ticks.checked_mul(100));
+        Ok(nanos.map(|nanos| SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos)))
+    }
+
+    fn uleb128(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_data("string length overflows"))
+    }
+
+    /// `0x00` for no string, or `0x0b`, a ULEB128 length and that many UTF-8 bytes.
+    fn string(&mut self) -> io::Result<Option<String>> {
+        match self.u8()? {
+            0x00 => Ok(None),
+            0x0b => {
+                let len = self.uleb128()?;
+                let mut buf = Vec::new();
+                (&mut self.0).take(len).read_to_end(&mut buf)?;
+                if (buf.len() as u64) < len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+            }
+            other => Err(invalid_data(&format!(
+                "unexpected string marker {other:#x}"
+            ))),
+        }
+    }
+
+    fn owned_string(&mut self) -> io::Result<String> {
+        Ok(self.string()?.unwrap_or_default())
+    }
+
+    fn beatmap(&mut self, version: u32) -> io::Result<DbBeatmap> {
+        if version < NO_ENTRY_SIZE_VERSION {
+            let _entry_size = self.u32()?;
+        }
+        let mut beatmap = DbBeatmap {
+            artist: self.owned_string()?,
+            artist_unicode: self.owned_string()?,
+            title: self.owned_string()?,
+            title_unicode: self.owned_string()?,
+            creator: self.owned_string()?,
+            version: self.owned_string()?,
+            audio_file_name: self.owned_string()?,
+            md5: self.owned_string()?,
+            osu_file_name: self.owned_string()?,
+            ranked_status: self.u8()?.into(),
+            ..Default::default()
+        };
+        // Hit object counts.
+        self.skip(3 * 2)?;
+        beatmap.last_modified = self.date_time()?;
+        // AR, CS, HP, OD, then slider velocity.
+        let difficulty_setting_size = if version < FLOAT_DIFFICULTY_VERSION {
+            1
+        } else {
+            4
+        };
+        self.skip(4 * difficulty_setting_size + 8)?;
+        if version >= FLOAT_DIFFICULTY_VERSION {
+            // Star ratings per mod combination, for each of the four modes.
+            let pair_size = if version >= SINGLE_STAR_RATING_VERSION {
+                1 + 4 + 1 + 4
+            } else {
+                1 + 4 + 1 + 8
+            };
+            for _ in 0..4 {
+                let pairs = self.u32()?;
+                self.skip(u64::from(pairs) * pair_size)?;
+            }
+        }
+        // Drain, total and preview times.
+        self.skip(3 * 4)?;
+        let timing_points = self.u32()?;
+        self.skip(u64::from(timing_points) * (8 + 8 + 1))?;
+        beatmap.beatmap_id = self.i32()?;
+        beatmap.beatmap_set_id = self.i32()?;
+        // Thread ID, grades, local offset, stack leniency and mode.
+        self.skip(4 + 4 + 2 + 4 + 1)?;
+        beatmap.source = self.owned_string()?;
+        beatmap.tags = self.owned_string()?;
+        // Online offset.
+        self.skip(2)?;
+        let _title_font = self.string()?;
+        // Unplayed, last played and osz2 flags.
+        self.skip(1 + 8 + 1)?;
+        beatmap.folder_name = self.owned_string()?;
+        // Last online check and the five per-map override flags.
+        self.skip(8 + 5)?;
+        if version < FLOAT_DIFFICULTY_VERSION {
+            self.skip(2)?;
+        }
+        // Last modification time and mania scroll speed.
+        self.skip(4 + 1)?;
+        Ok(beatmap)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn push_string(buf: &mut Vec<u8>, s: &str) {
+        buf.push(0x0b);
+        let mut len = s.len();
+        loop {
+            let byte = (len & 0x7f) as u8;
+            len >>= 7;
+            if len == 0 {
+                buf.push(byte);
+                break;
+            }
+            buf.push(byte | 0x80);
+        }
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn push_beatmap(buf: &mut Vec<u8>, version: u32, folder: &str, beatmap_id: i32, set_id: i32) {
+        for s in [
+            "Artist",
+            "Artist",
+            "Title",
+            "Title",
+            "Mapper",
+            "Insane",
+            "audio.mp3",
+        ] {
+            push_string(buf, s);
+        }
+        push_string(buf, "0123456789abcdef0123456789abcdef");
+        push_string(buf, &format!("{folder}.osu"));
+        buf.push(if beatmap_id == 0 { 1 } else { 4 });
+        buf.extend([0; 3 * 2]);
+        let ticks = if beatmap_id == 0 {
+            0
+        } else {
+            UNIX_EPOCH_TICKS + 1_600_000_000 * 10_000_000
+        };
+        buf.extend(ticks.to_le_bytes());
+        buf.extend([0; 4 * 4 + 8]);
+        let pair_size = if version >= SINGLE_STAR_RATING_VERSION {
+            10
+        } else {
+            14
+        };
+        for _ in 0..4 {
+            buf.extend(2u32.to_le_bytes());
+            buf.extend(vec![0; 2 * pair_size]);
+        }
+        buf.extend([0; 3 * 4]);
+        buf.extend(1u32.to_le_bytes());
+        buf.extend([0; 17]);
+        buf.extend(beatmap_id.to_le_bytes());
+        buf.extend(set_id.to_le_bytes());
+        buf.extend([0; 4 + 4 + 2 + 4 + 1]);
+        push_string(buf, "");
+        push_string(buf, "tag1 tag2");
+        buf.extend([0; 2]);
+        buf.push(0x00);
+        buf.extend([0; 1 + 8 + 1]);
+        push_string(buf, folder);
+        buf.extend([0; 8 + 5]);
+        buf.extend([0; 4 + 1]);
+    }
+
+    fn db(version: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(version.to_le_bytes());
+        buf.extend(2u32.to_le_bytes());
+        buf.push(1);
+        buf.extend([0; 8]);
+        push_string(&mut buf, "player");
+        buf.extend(2u32.to_le_bytes());
+        push_beatmap(&mut buf, version, "1 Artist - Title", 11, 1);
+        push_beatmap(&mut buf, version, "Unsubmitted", 0, -1);
+        buf.extend(0u32.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn test_read_osu_db() {
+        for version in [20191107, SINGLE_STAR_RATING_VERSION] {
+            let db = OsuDb::read(db(version).as_slice()).unwrap();
+            assert_eq!(db.player_name.as_deref(), Some("player"));
+            assert_eq!(db.beatmaps.len(), 2);
+            let submitted = &db.beatmaps[0];
+            assert_eq!(submitted.folder_name, "1 Artist - Title");
+            assert_eq!(submitted.osu_file_name, "1 Artist - Title.osu");
+            assert_eq!(submitted.ranked_status, RankedStatus::Ranked);
+            assert_eq!(submitted.metadata().beatmap_id, Some(11));
+            assert_eq!(submitted.metadata().tags, vec!["tag1", "tag2"]);
+            assert_eq!(
+                submitted.last_modified,
+                Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+            );
+            let unsubmitted = &db.beatmaps[1];
+            assert_eq!(unsubmitted.ranked_status, RankedStatus::Unsubmitted);
+            assert_eq!(unsubmitted.metadata().beatmap_set_id, Some(-1));
+            assert_eq!(unsubmitted.last_modified, None);
+        }
+        assert!(OsuDb::read(&db(20191107)[..100]).is_err());
+    }
+
+    #[test]
+    fn test_date_time_out_of_range() {
+        let date_time = |ticks: u64| DbReader(&ticks.to_le_bytes()[..]).date_time().unwrap();
+        assert_eq!(date_time(0), None);
+        assert_eq!(date_time(UNIX_EPOCH_TICKS - 1), None);
+        assert_eq!(
+            date_time(UNIX_EPOCH_TICKS + 10_000_000),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1))
+        );
+        assert_eq!(date_time(u64::MAX), None);
+    }
+}
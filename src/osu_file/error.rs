@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// A problem found while parsing a `.osu` file, tied to the (1-based) line it was found on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(line: usize, kind: ParseErrorKind) -> Self {
+        Self { line, kind }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// The file has no `osu file format vN` line before the first section.
+    MissingHeader,
+    /// The header line exists but the version couldn't be read.
+    InvalidHeader(String),
+    /// A `[Section]` we don't know about. Its contents are skipped.
+    UnknownSection(String),
+    /// A `Key: Value` line without the `:`.
+    MissingSeparator,
+    /// A comma separated line with too few fields.
+    MissingField(&'static str),
+    /// A field that couldn't be converted to the expected type.
+    InvalidValue { field: String, value: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::MissingHeader => write!(f, "missing `osu file format` header"),
+            ParseErrorKind::InvalidHeader(header) => write!(f, "invalid header `{header}`"),
+            ParseErrorKind::UnknownSection(section) => write!(f, "unknown section [{section}]"),
+            ParseErrorKind::MissingSeparator => write!(f, "expected `Key: Value`"),
+            ParseErrorKind::MissingField(field) => write!(f, "missing field `{field}`"),
+            ParseErrorKind::InvalidValue { field, value } => {
+                write!(f, "invalid value `{value}` for `{field}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,128 @@
+use super::{parse_value, ParseErrorKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Background {
+        filename: String,
+        x_offset: i32,
+        y_offset: i32,
+    },
+    Video {
+        start_time: i32,
+        filename: String,
+        x_offset: i32,
+        y_offset: i32,
+    },
+    Break {
+        start_time: i32,
+        end_time: i32,
+    },
+    Sprite {
+        layer: String,
+        origin: String,
+        filepath: String,
+        x: f64,
+        y: f64,
+    },
+    Animation {
+        layer: String,
+        origin: String,
+        filepath: String,
+        x: f64,
+        y: f64,
+        frame_count: u32,
+        frame_delay: f64,
+        loop_type: String,
+    },
+    Sample {
+        time: i32,
+        layer: String,
+        filepath: String,
+        volume: u8,
+    },
+    /// An indented storyboard command belonging to the previous sprite/animation.
+    /// Kept verbatim as nothing in the extractor needs to interpret them.
+    Command(String),
+    /// Anything else (legacy background colour events etc.), kept verbatim.
+    Other(String),
+}
+
+impl Event {
+    pub(super) fn parse(line: &str) -> Result<Self, ParseErrorKind> {
+        if line.starts_with([' ', '_']) {
+            return Ok(Event::Command(line.to_owned()));
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |i: usize, name: &'static str| {
+            fields
+                .get(i)
+                .copied()
+                .ok_or(ParseErrorKind::MissingField(name))
+        };
+        let optional_offset = |i: usize, name: &str| match fields.get(i) {
+            Some(v) => parse_value(name, v),
+            None => Ok(0),
+        };
+
+        let event = match fields[0] {
+            "0" | "Background" => Event::Background {
+                filename: unquote(field(2, "filename")?),
+                x_offset: optional_offset(3, "xOffset")?,
+                y_offset: optional_offset(4, "yOffset")?,
+            },
+            "1" | "Video" => Event::Video {
+                start_time: parse_value("startTime", field(1, "startTime")?)?,
+                filename: unquote(field(2, "filename")?),
+                x_offset: optional_offset(3, "xOffset")?,
+                y_offset: optional_offset(4, "yOffset")?,
+            },
+            "2" | "Break" => Event::Break {
+                start_time: parse_value("startTime", field(1, "startTime")?)?,
+                end_time: parse_value("endTime", field(2, "endTime")?)?,
+            },
+            "4" | "Sprite" => Event::Sprite {
+                layer: field(1, "layer")?.to_owned(),
+                origin: field(2, "origin")?.to_owned(),
+                filepath: unquote(field(3, "filepath")?),
+                x: parse_value("x", field(4, "x")?)?,
+                y: parse_value("y", field(5, "y")?)?,
+            },
+            "6" | "Animation" => Event::Animation {
+                layer: field(1, "layer")?.to_owned(),
+                origin: field(2, "origin")?.to_owned(),
+                filepath: unquote(field(3, "filepath")?),
+                x: parse_value("x", field(4, "x")?)?,
+                y: parse_value("y", field(5, "y")?)?,
+                frame_count: parse_value("frameCount", field(6, "frameCount")?)?,
+                frame_delay: parse_value("frameDelay", field(7, "frameDelay")?)?,
+                loop_type: fields.get(8).unwrap_or(&"LoopForever").to_string(),
+            },
+            "5" | "Sample" => Event::Sample {
+                time: parse_value("time", field(1, "time")?)?,
+                layer: field(2, "layer")?.to_owned(),
+                filepath: unquote(field(3, "filepath")?),
+                volume: match fields.get(4) {
+                    Some(v) => parse_value("volume", v)?,
+                    None => 100,
+                },
+            },
+            _ => Event::Other(line.to_owned()),
+        };
+        Ok(event)
+    }
+
+    /// The file inside the song folder this event needs, if any.
+    pub fn filename(&self) -> Option<&str> {
+        match self {
+            Event::Background { filename, .. } | Event::Video { filename, .. } => Some(filename),
+            Event::Sprite { filepath, .. }
+            | Event::Animation { filepath, .. }
+            | Event::Sample { filepath, .. } => Some(filepath),
+            Event::Break { .. } | Event::Command(_) | Event::Other(_) => None,
+        }
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches('"').to_owned()
+}
@@ -0,0 +1,140 @@
+use std::io::{self, BufRead};
+
+use super::{
+    apply_key_value, parse_header, Difficulty, General, Metadata, ParseError, ParseErrorKind,
+    Section, DEFAULT_FORMAT_VERSION, HEADER_PREFIX,
+};
+
+/// The sections before [Events], for callers that need a difficulty's IDs and details but
+/// not the map itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OsuHeader {
+    pub format_version: u32,
+    pub general: General,
+    pub metadata: Metadata,
+    pub difficulty: Difficulty,
+}
+
+impl OsuHeader {
+    /// Reads lines until [Metadata] and [Difficulty] are both done or the first section past
+    /// them starts, leaving the rest of `reader` unread. Bad lines are skipped and returned
+    /// alongside the result like [`OsuFile::parse_lenient`](super::OsuFile::parse_lenient).
+    ///
+    /// Bytes that aren't UTF-8, e.g. a title saved in a legacy code page, are replaced rather
+    /// than failing the file. A missing header is returned like a bad line, an invalid one is
+    /// an [`io::ErrorKind::InvalidData`].
+    pub fn read(reader: &mut impl BufRead) -> io::Result<(Self, Vec<ParseError>)> {
+        let mut header = OsuHeader {
+            format_version: DEFAULT_FORMAT_VERSION,
+            ..Default::default()
+        };
+        let mut errors = Vec::new();
+        let mut section = None;
+        let mut seen_metadata = false;
+        let mut seen_difficulty = false;
+        let mut buf = Vec::new();
+
+        for line_number in 1.. {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&buf);
+            let line = if line_number == 1 {
+                line.trim_start_matches('\u{feff}')
+            } else {
+                &line
+            };
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with("//") {
+                continue;
+            }
+
+            let current = match section {
+                Some(current) => current,
+                None => {
+                    section = Some(Section::Unknown);
+                    if trimmed.starts_with(HEADER_PREFIX) {
+                        header.format_version = parse_header(trimmed, line_number)
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                        continue;
+                    }
+                    errors.push(ParseError::new(line_number, ParseErrorKind::MissingHeader));
+                    Section::Unknown
+                }
+            };
+
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                let name = &trimmed[1..trimmed.len() - 1];
+                let next = Section::from_name(name);
+                if (seen_metadata && seen_difficulty)
+                    || (next != Section::Unknown && !next.is_header())
+                {
+                    break;
+                }
+                match next {
+                    Section::Metadata => seen_metadata = true,
+                    Section::Difficulty => seen_difficulty = true,
+                    Section::Unknown => errors.push(ParseError::new(
+                        line_number,
+                        ParseErrorKind::UnknownSection(name.to_owned()),
+                    )),
+                    _ => {}
+                }
+                section = Some(next);
+                continue;
+            }
+
+            let result = match current {
+                Section::General => apply_key_value(&mut header.general, trimmed),
+                Section::Metadata => apply_key_value(&mut header.metadata, trimmed),
+                Section::Difficulty => apply_key_value(&mut header.difficulty, trimmed),
+                _ => Ok(()),
+            };
+            if let Err(kind) = result {
+                errors.push(ParseError::new(line_number, kind));
+            }
+        }
+
+        if section.is_none() {
+            errors.push(ParseError::new(1, ParseErrorKind::MissingHeader));
+        }
+        Ok((header, errors))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_header() {
+        let mut contents = b"\xef\xbb\xbfosu file format v14\r\n\r\n[General]\r\nAudioFilename: a.mp3\r\n\r\n[Metadata]\r\nTitle:caf\xe9\r\nCreator:me\r\nBeatmapID:12\r\nBeatmapSetID:-1\r\n\r\n[Difficulty]\r\nCircleSize:4\r\n\r\n[Events]\r\n".to_vec();
+        let rest = b"not an event\n[HitObjects]\n1,2\n";
+        contents.extend_from_slice(rest);
+
+        let mut reader = &contents[..];
+        let (header, errors) = OsuHeader::read(&mut reader).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(header.format_version, 14);
+        assert_eq!(header.general.audio_filename, "a.mp3");
+        assert_eq!(header.metadata.title, "caf\u{fffd}");
+        assert_eq!(header.metadata.creator, "me");
+        assert_eq!(header.metadata.beatmap_id, Some(12));
+        assert_eq!(header.metadata.beatmap_set_id, Some(-1));
+        assert_eq!(header.difficulty.circle_size, 4.0);
+        assert_eq!(reader, rest);
+
+        let (header, errors) =
+            OsuHeader::read(&mut &b"[Metadata]\nBeatmapID:5\n[Difficulty]\n"[..]).unwrap();
+        assert_eq!(header.format_version, DEFAULT_FORMAT_VERSION);
+        assert_eq!(header.metadata.beatmap_id, Some(5));
+        assert_eq!(
+            errors,
+            vec![ParseError::new(1, ParseErrorKind::MissingHeader)]
+        );
+
+        let err = OsuHeader::read(&mut &b"osu file format vx\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
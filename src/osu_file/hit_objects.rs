@@ -0,0 +1,208 @@
+use super::{parse_bool, parse_int, parse_value, ParseErrorKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingPoint {
+    pub time: f64,
+    pub beat_length: f64,
+    pub meter: i32,
+    pub sample_set: u8,
+    pub sample_index: u32,
+    pub volume: u8,
+    pub uninherited: bool,
+    pub effects: u8,
+}
+
+impl TimingPoint {
+    pub(super) fn parse(line: &str) -> Result<Self, ParseErrorKind> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let required = |i: usize, name: &'static str| {
+            fields
+                .get(i)
+                .copied()
+                .ok_or(ParseErrorKind::MissingField(name))
+        };
+        // Everything after beatLength was added in later format versions.
+        let optional = |i: usize| fields.get(i).copied().filter(|f| !f.is_empty());
+
+        Ok(Self {
+            time: parse_value("time", required(0, "time")?)?,
+            beat_length: parse_value("beatLength", required(1, "beatLength")?)?,
+            meter: optional(2).map_or(Ok(4), |v| parse_int("meter", v))?,
+            sample_set: optional(3).map_or(Ok(0), |v| parse_value("sampleSet", v))?,
+            sample_index: optional(4).map_or(Ok(0), |v| parse_value("sampleIndex", v))?,
+            volume: optional(5).map_or(Ok(100), |v| parse_value("volume", v))?,
+            uninherited: optional(6).map_or(Ok(true), |v| parse_bool("uninherited", v))?,
+            effects: optional(7).map_or(Ok(0), |v| parse_value("effects", v))?,
+        })
+    }
+}
+
+/// The `normalSet:additionSet:index:volume:filename` tail of a hit object.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HitSample {
+    pub normal_set: u8,
+    pub addition_set: u8,
+    pub index: u32,
+    pub volume: u8,
+    /// A custom sample file overriding the usual hitsound lookup.
+    pub filename: Option<String>,
+}
+
+impl HitSample {
+    fn parse(value: &str) -> Result<Self, ParseErrorKind> {
+        let mut sample = HitSample::default();
+        for (i, part) in value.split(':').enumerate() {
+            if part.is_empty() {
+                continue;
+            }
+            match i {
+                0 => sample.normal_set = parse_value("normalSet", part)?,
+                1 => sample.addition_set = parse_value("additionSet", part)?,
+                2 => sample.index = parse_value("index", part)?,
+                3 => sample.volume = parse_value("volume", part)?,
+                4 => sample.filename = Some(part.to_owned()),
+                _ => {}
+            }
+        }
+        Ok(sample)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HitObjectKind {
+    Circle,
+    Slider {
+        curve_type: char,
+        curve_points: Vec<(i32, i32)>,
+        slides: u32,
+        length: f64,
+        edge_sounds: Vec<u8>,
+        edge_sets: Vec<(u8, u8)>,
+    },
+    Spinner {
+        end_time: i32,
+    },
+    ManiaHold {
+        end_time: i32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitObject {
+    pub x: i32,
+    pub y: i32,
+    pub time: i32,
+    pub kind: HitObjectKind,
+    pub new_combo: bool,
+    pub combo_skip: u8,
+    pub hit_sound: u8,
+    pub hit_sample: HitSample,
+}
+
+impl HitObject {
+    pub(super) fn parse(line: &str) -> Result<Self, ParseErrorKind> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let required = |i: usize, name: &'static str| {
+            fields
+                .get(i)
+                .copied()
+                .ok_or(ParseErrorKind::MissingField(name))
+        };
+
+        let type_bits: u8 = parse_value("type", required(3, "type")?)?;
+        let hit_sample;
+        let kind = if type_bits & 1 != 0 {
+            hit_sample = fields.get(5);
+            HitObjectKind::Circle
+        } else if type_bits & 2 != 0 {
+            let curve = required(5, "curvePoints")?;
+            let (curve_type, points) = curve.split_once('|').unwrap_or((curve, ""));
+            let curve_points = points
+                .split('|')
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    let (x, y) = p.split_once(':').ok_or(ParseErrorKind::InvalidValue {
+                        field: "curvePoints".to_owned(),
+                        value: p.to_owned(),
+                    })?;
+                    Ok((parse_int("curvePoints", x)?, parse_int("curvePoints", y)?))
+                })
+                .collect::<Result<_, ParseErrorKind>>()?;
+            let edge_sounds = match fields.get(8) {
+                Some(v) if !v.is_empty() => v
+                    .split('|')
+                    .map(|s| parse_value("edgeSounds", s))
+                    .collect::<Result<_, _>>()?,
+                _ => Vec::new(),
+            };
+            let edge_sets = match fields.get(9) {
+                Some(v) if !v.is_empty() => v
+                    .split('|')
+                    .map(|s| {
+                        let (normal, addition) = s.split_once(':').unwrap_or((s, "0"));
+                        Ok((
+                            parse_value("edgeSets", normal)?,
+                            parse_value("edgeSets", addition)?,
+                        ))
+                    })
+                    .collect::<Result<_, ParseErrorKind>>()?,
+                _ => Vec::new(),
+            };
+            hit_sample = fields.get(10);
+            HitObjectKind::Slider {
+                curve_type: curve_type.chars().next().unwrap_or('B'),
+                curve_points,
+                slides: parse_value("slides", required(6, "slides")?)?,
+                length: parse_value("length", required(7, "length")?)?,
+                edge_sounds,
+                edge_sets,
+            }
+        } else if type_bits & 8 != 0 {
+            hit_sample = fields.get(6);
+            HitObjectKind::Spinner {
+                end_time: parse_int("endTime", required(5, "endTime")?)?,
+            }
+        } else if type_bits & 128 != 0 {
+            // Holds pack the hit sample onto the end time: `endTime:normalSet:...`.
+            let end_time = required(5, "endTime")?;
+            let (end_time, sample) = end_time.split_once(':').unwrap_or((end_time, ""));
+            let end_time = parse_int("endTime", end_time)?;
+            return Ok(Self {
+                hit_sample: HitSample::parse(sample)?,
+                ..Self::common(&fields, type_bits, HitObjectKind::ManiaHold { end_time })?
+            });
+        } else {
+            return Err(ParseErrorKind::InvalidValue {
+                field: "type".to_owned(),
+                value: type_bits.to_string(),
+            });
+        };
+
+        Ok(Self {
+            hit_sample: match hit_sample {
+                Some(sample) => HitSample::parse(sample)?,
+                None => HitSample::default(),
+            },
+            ..Self::common(&fields, type_bits, kind)?
+        })
+    }
+
+    fn common(fields: &[&str], type_bits: u8, kind: HitObjectKind) -> Result<Self, ParseErrorKind> {
+        let required = |i: usize, name: &'static str| {
+            fields
+                .get(i)
+                .copied()
+                .ok_or(ParseErrorKind::MissingField(name))
+        };
+        Ok(Self {
+            x: parse_int("x", required(0, "x")?)?,
+            y: parse_int("y", required(1, "y")?)?,
+            time: parse_int("time", required(2, "time")?)?,
+            kind,
+            new_combo: type_bits & 4 != 0,
+            combo_skip: (type_bits >> 4) & 0b111,
+            hit_sound: parse_value("hitSound", required(4, "hitSound")?)?,
+            hit_sample: HitSample::default(),
+        })
+    }
+}
@@ -0,0 +1,434 @@
+//! Parser for the `.osu` difficulty file format.
+//!
+//! Format reference: https://osu.ppy.sh/wiki/en/Client/File_formats/osu_%28file_format%29
+
+use std::str::FromStr;
+
+mod error;
+mod events;
+mod header;
+mod hit_objects;
+mod sections;
+
+pub use error::{ParseError, ParseErrorKind};
+pub use events::Event;
+pub use header::OsuHeader;
+pub use hit_objects::{HitObject, HitObjectKind, HitSample, TimingPoint};
+pub use sections::{Colour, Colours, Difficulty, Editor, General, Metadata};
+
+use sections::KeyValueSection;
+
+const HEADER_PREFIX: &str = "osu file format v";
+/// Assumed for files without a header, it's the version the game writes today.
+pub const DEFAULT_FORMAT_VERSION: u32 = 14;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OsuFile {
+    pub format_version: u32,
+    pub general: General,
+    pub editor: Editor,
+    pub metadata: Metadata,
+    pub difficulty: Difficulty,
+    pub events: Vec<Event>,
+    pub timing_points: Vec<TimingPoint>,
+    pub colours: Colours,
+    pub hit_objects: Vec<HitObject>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    General,
+    Editor,
+    Metadata,
+    Difficulty,
+    Events,
+    TimingPoints,
+    Colours,
+    HitObjects,
+    Unknown,
+}
+
+impl OsuFile {
+    /// Parses a whole file, failing on the first bad line.
+    pub fn parse(contents: &str) -> Result<Self, ParseError> {
+        let (file, mut errors) = Self::parse_lenient(contents)?;
+        if errors.is_empty() {
+            Ok(file)
+        } else {
+            Err(errors.swap_remove(0))
+        }
+    }
+
+    /// Parses a whole file, skipping bad lines and returning them alongside the result.
+    ///
+    /// Only an invalid header is fatal, everything else the game itself would tolerate. A
+    /// missing one is returned like a bad line and the file read as [`DEFAULT_FORMAT_VERSION`].
+    pub fn parse_lenient(contents: &str) -> Result<(Self, Vec<ParseError>), ParseError> {
+        let mut file = OsuFile {
+            format_version: DEFAULT_FORMAT_VERSION,
+            ..Default::default()
+        };
+        let mut errors = Vec::new();
+        let mut section = None;
+
+        for (i, line) in contents.lines().enumerate() {
+            let line_number = i + 1;
+            let line = if i == 0 {
+                line.trim_start_matches('\u{feff}')
+            } else {
+                line
+            };
+            // Storyboard commands are indented, so only trim the right for events.
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with("//") {
+                continue;
+            }
+
+            let current = match section {
+                Some(current) => current,
+                None => {
+                    section = Some(Section::Unknown);
+                    if trimmed.starts_with(HEADER_PREFIX) {
+                        file.format_version = parse_header(trimmed, line_number)?;
+                        continue;
+                    }
+                    // Without a header this line is already part of the file.
+                    errors.push(ParseError::new(line_number, ParseErrorKind::MissingHeader));
+                    Section::Unknown
+                }
+            };
+
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                let name = &trimmed[1..trimmed.len() - 1];
+                let next = Section::from_name(name);
+                if next == Section::Unknown {
+                    errors.push(ParseError::new(
+                        line_number,
+                        ParseErrorKind::UnknownSection(name.to_owned()),
+                    ));
+                }
+                section = Some(next);
+                continue;
+            }
+
+            let result = match current {
+                Section::General => apply_key_value(&mut file.general, trimmed),
+                Section::Editor => apply_key_value(&mut file.editor, trimmed),
+                Section::Metadata => apply_key_value(&mut file.metadata, trimmed),
+                Section::Difficulty => apply_key_value(&mut file.difficulty, trimmed),
+                Section::Colours => apply_key_value(&mut file.colours, trimmed),
+                Section::Events => Event::parse(line.trim_end()).map(|e| file.events.push(e)),
+                Section::TimingPoints => {
+                    TimingPoint::parse(trimmed).map(|t| file.timing_points.push(t))
+                }
+                Section::HitObjects => HitObject::parse(trimmed).map(|h| file.hit_objects.push(h)),
+                Section::Unknown => Ok(()),
+            };
+            if let Err(kind) = result {
+                errors.push(ParseError::new(line_number, kind));
+            }
+        }
+
+        if section.is_none() {
+            errors.push(ParseError::new(1, ParseErrorKind::MissingHeader));
+        }
+        Ok((file, errors))
+    }
+}
+
+/// Parses the events out of a standalone `.osb` storyboard, which has no header and may
+/// declare `$variables` in a `[Variables]` section.
+pub fn parse_storyboard(contents: &str) -> (Vec<Event>, Vec<ParseError>) {
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+    let mut variables: Vec<(String, String)> = Vec::new();
+    let mut section = "";
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        }
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            section = &trimmed[1..trimmed.len() - 1];
+            continue;
+        }
+        match section {
+            "Variables" => match trimmed.split_once('=') {
+                Some((name, value)) => variables.push((name.to_owned(), value.to_owned())),
+                None => errors.push(ParseError::new(i + 1, ParseErrorKind::MissingSeparator)),
+            },
+            "Events" => {
+                let mut line = line.to_owned();
+                for (name, value) in &variables {
+                    line = line.replace(name, value);
+                }
+                match Event::parse(&line) {
+                    Ok(event) => events.push(event),
+                    Err(kind) => errors.push(ParseError::new(i + 1, kind)),
+                }
+            }
+            _ => {}
+        }
+    }
+    (events, errors)
+}
+
+impl FromStr for OsuFile {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Section {
+    /// The sections before [Events], holding everything but the map itself.
+    fn is_header(self) -> bool {
+        matches!(
+            self,
+            Section::General | Section::Editor | Section::Metadata | Section::Difficulty
+        )
+    }
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "General" => Section::General,
+            "Editor" => Section::Editor,
+            "Metadata" => Section::Metadata,
+            "Difficulty" => Section::Difficulty,
+            "Events" => Section::Events,
+            "TimingPoints" => Section::TimingPoints,
+            "Colours" => Section::Colours,
+            "HitObjects" => Section::HitObjects,
+            _ => Section::Unknown,
+        }
+    }
+}
+
+/// The format version from the `osu file format vN` line every file starts with.
+fn parse_header(trimmed: &str, line_number: usize) -> Result<u32, ParseError> {
+    let Some(version) = trimmed.strip_prefix(HEADER_PREFIX) else {
+        return Err(ParseError::new(line_number, ParseErrorKind::MissingHeader));
+    };
+    version.trim().parse().map_err(|_| {
+        ParseError::new(
+            line_number,
+            ParseErrorKind::InvalidHeader(trimmed.to_owned()),
+        )
+    })
+}
+
+fn apply_key_value<S: KeyValueSection>(section: &mut S, line: &str) -> Result<(), ParseErrorKind> {
+    let (key, value) = line
+        .split_once(':')
+        .ok_or(ParseErrorKind::MissingSeparator)?;
+    section.apply(key.trim(), value.trim())
+}
+
+fn parse_value<T: FromStr>(field: &str, value: &str) -> Result<T, ParseErrorKind> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ParseErrorKind::InvalidValue {
+            field: field.to_owned(),
+            value: value.to_owned(),
+        })
+}
+
+/// Integers that some older editors wrote out as decimals, e.g. `256.5`.
+fn parse_int(field: &str, value: &str) -> Result<i32, ParseErrorKind> {
+    parse_value::<i32>(field, value).or_else(|e| {
+        parse_value::<f64>(field, value)
+            .map(|f| f as i32)
+            .map_err(|_| e)
+    })
+}
+
+fn parse_bool(field: &str, value: &str) -> Result<bool, ParseErrorKind> {
+    match value.trim() {
+        "1" | "true" | "True" => Ok(true),
+        "0" | "false" | "False" => Ok(false),
+        _ => Err(ParseErrorKind::InvalidValue {
+            field: field.to_owned(),
+            value: value.to_owned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "\u{feff}osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+AudioLeadIn: 0
+PreviewTime: 41733
+Countdown: 0
+SampleSet: Soft
+StackLeniency: 0.5
+Mode: 0
+LetterboxInBreaks: 0
+WidescreenStoryboard: 1
+
+[Editor]
+Bookmarks: 1000,2000
+DistanceSpacing: 1.2
+BeatDivisor: 4
+GridSize: 8
+TimelineZoom: 2.5
+
+[Metadata]
+Title:NeverSayDie
+TitleUnicode:NeverSayDie
+Artist:5StepSoundTeam
+ArtistUnicode:5StepSoundTeam
+Creator:PoMuTa
+Version:Die
+Source:5StepAdventure
+Tags:lapfox video game
+BeatmapID:0
+BeatmapSetID:-1
+
+[Difficulty]
+HPDrainRate:5.8
+CircleSize:4
+OverallDifficulty:9.8
+ApproachRate:9.5
+SliderMultiplier:1.8
+SliderTickRate:1
+
+[Events]
+//Background and Video events
+0,0,\"bg.jpg\",0,0
+Video,-200,\"video.avi\"
+//Break Periods
+2,10000,12000
+//Storyboard Layer 0 (Background)
+Sprite,Foreground,Centre,\"sb\\star.png\",320,240
+ F,0,1000,2000,0,1
+Sample,500,0,\"clap.wav\",70
+
+[TimingPoints]
+240,240,4,2,1,60,1,0
+1200,-50,4,2,2,50,0,1
+
+[Colours]
+Combo1 : 255,128,0
+SliderBorder : 10,20,30
+
+[HitObjects]
+256,192,240,5,0,0:0:0:0:
+100,100,480,2,2,B|200:200|300:100,1,140,2|0,0:0|1:2,0:0:0:0:
+256,192,1000,12,0,2000,0:0:0:0:custom.wav
+64,192,3000,128,0,3500:0:0:0:0:
+";
+
+    #[test]
+    fn test_parse_full_file() {
+        let file = OsuFile::parse(EXAMPLE).unwrap();
+        assert_eq!(file.format_version, 14);
+        assert_eq!(file.general.audio_filename, "audio.mp3");
+        assert_eq!(file.general.sample_set, "Soft");
+        assert!(file.general.widescreen_storyboard);
+        assert_eq!(file.editor.bookmarks, vec![1000, 2000]);
+        assert_eq!(file.metadata.artist, "5StepSoundTeam");
+        assert_eq!(file.metadata.tags, vec!["lapfox", "video", "game"]);
+        assert_eq!(file.metadata.beatmap_id, Some(0));
+        assert_eq!(file.metadata.beatmap_set_id, Some(-1));
+        assert_eq!(file.difficulty.approach_rate, Some(9.5));
+
+        let filenames: Vec<&str> = file.events.iter().filter_map(Event::filename).collect();
+        assert_eq!(
+            filenames,
+            vec!["bg.jpg", "video.avi", "sb\\star.png", "clap.wav"]
+        );
+        assert!(matches!(file.events[4], Event::Command(_)));
+
+        assert_eq!(file.timing_points.len(), 2);
+        assert!(!file.timing_points[1].uninherited);
+        assert_eq!(file.timing_points[1].sample_index, 2);
+
+        assert_eq!(
+            file.colours.combos,
+            vec![Colour {
+                r: 255,
+                g: 128,
+                b: 0
+            }]
+        );
+        assert_eq!(
+            file.colours.slider_border,
+            Some(Colour {
+                r: 10,
+                g: 20,
+                b: 30
+            })
+        );
+
+        assert_eq!(file.hit_objects.len(), 4);
+        assert!(file.hit_objects[0].new_combo);
+        match &file.hit_objects[1].kind {
+            HitObjectKind::Slider {
+                curve_type,
+                curve_points,
+                edge_sets,
+                ..
+            } => {
+                assert_eq!(*curve_type, 'B');
+                assert_eq!(curve_points, &vec![(200, 200), (300, 100)]);
+                assert_eq!(edge_sets, &vec![(0, 0), (1, 2)]);
+            }
+            other => panic!("expected slider, got {other:?}"),
+        }
+        assert_eq!(
+            file.hit_objects[2].kind,
+            HitObjectKind::Spinner { end_time: 2000 }
+        );
+        assert_eq!(
+            file.hit_objects[2].hit_sample.filename.as_deref(),
+            Some("custom.wav")
+        );
+        assert_eq!(
+            file.hit_objects[3].kind,
+            HitObjectKind::ManiaHold { end_time: 3500 }
+        );
+    }
+
+    #[test]
+    fn test_parse_reports_line_numbers() {
+        let contents =
+            "osu file format v14\n\n[Metadata]\nBeatmapID:abc\nTitle:ok\n[HitObjects]\n1,2\n";
+        let (file, errors) = OsuFile::parse_lenient(contents).unwrap();
+        assert_eq!(file.metadata.title, "ok");
+        assert_eq!(file.metadata.beatmap_id, None);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].line, 4);
+        assert_eq!(errors[1].line, 7);
+        assert_eq!(errors[1].kind, ParseErrorKind::MissingField("type"));
+
+        assert_eq!(OsuFile::parse(contents).unwrap_err().line, 4);
+    }
+
+    #[test]
+    fn test_parse_missing_header() {
+        let err = OsuFile::parse("[General]\nAudioFilename: a.mp3\n").unwrap_err();
+        assert_eq!(err, ParseError::new(1, ParseErrorKind::MissingHeader));
+        let err = OsuFile::parse("").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::MissingHeader);
+
+        let (file, errors) =
+            OsuFile::parse_lenient("[General]\nAudioFilename: a.mp3\n[Metadata]\nBeatmapID:5\n")
+                .unwrap();
+        assert_eq!(file.format_version, DEFAULT_FORMAT_VERSION);
+        assert_eq!(file.general.audio_filename, "a.mp3");
+        assert_eq!(file.metadata.beatmap_id, Some(5));
+        assert_eq!(
+            errors,
+            vec![ParseError::new(1, ParseErrorKind::MissingHeader)]
+        );
+    }
+}
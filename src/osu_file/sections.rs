@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use super::{parse_bool, parse_value, ParseErrorKind};
+
+/// The `Key: Value` sections. Unknown keys are ignored so newer format versions still parse.
+pub(super) trait KeyValueSection {
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), ParseErrorKind>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct General {
+    pub audio_filename: String,
+    pub audio_lead_in: i32,
+    pub preview_time: i32,
+    pub countdown: u8,
+    pub sample_set: String,
+    pub stack_leniency: f64,
+    pub mode: u8,
+    pub letterbox_in_breaks: bool,
+    pub use_skin_sprites: bool,
+    pub overlay_position: String,
+    pub skin_preference: String,
+    pub epilepsy_warning: bool,
+    pub countdown_offset: i32,
+    pub special_style: bool,
+    pub widescreen_storyboard: bool,
+    pub samples_match_playback_rate: bool,
+}
+
+impl Default for General {
+    fn default() -> Self {
+        Self {
+            audio_filename: String::new(),
+            audio_lead_in: 0,
+            preview_time: -1,
+            countdown: 1,
+            sample_set: "Normal".to_owned(),
+            stack_leniency: 0.7,
+            mode: 0,
+            letterbox_in_breaks: false,
+            use_skin_sprites: false,
+            overlay_position: "NoChange".to_owned(),
+            skin_preference: String::new(),
+            epilepsy_warning: false,
+            countdown_offset: 0,
+            special_style: false,
+            widescreen_storyboard: false,
+            samples_match_playback_rate: false,
+        }
+    }
+}
+
+impl KeyValueSection for General {
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), ParseErrorKind> {
+        match key {
+            "AudioFilename" => self.audio_filename = value.to_owned(),
+            "AudioLeadIn" => self.audio_lead_in = parse_value(key, value)?,
+            "PreviewTime" => self.preview_time = parse_value(key, value)?,
+            "Countdown" => self.countdown = parse_value(key, value)?,
+            "SampleSet" => self.sample_set = value.to_owned(),
+            "StackLeniency" => self.stack_leniency = parse_value(key, value)?,
+            "Mode" => self.mode = parse_value(key, value)?,
+            "LetterboxInBreaks" => self.letterbox_in_breaks = parse_bool(key, value)?,
+            "UseSkinSprites" => self.use_skin_sprites = parse_bool(key, value)?,
+            "OverlayPosition" => self.overlay_position = value.to_owned(),
+            "SkinPreference" => self.skin_preference = value.to_owned(),
+            "EpilepsyWarning" => self.epilepsy_warning = parse_bool(key, value)?,
+            "CountdownOffset" => self.countdown_offset = parse_value(key, value)?,
+            "SpecialStyle" => self.special_style = parse_bool(key, value)?,
+            "WidescreenStoryboard" => self.widescreen_storyboard = parse_bool(key, value)?,
+            "SamplesMatchPlaybackRate" => {
+                self.samples_match_playback_rate = parse_bool(key, value)?
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Editor {
+    pub bookmarks: Vec<i32>,
+    pub distance_spacing: f64,
+    pub beat_divisor: i32,
+    pub grid_size: i32,
+    pub timeline_zoom: f64,
+}
+
+impl KeyValueSection for Editor {
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), ParseErrorKind> {
+        match key {
+            "Bookmarks" => {
+                self.bookmarks = value
+                    .split(',')
+                    .filter(|b| !b.trim().is_empty())
+                    .map(|b| parse_value(key, b.trim()))
+                    .collect::<Result<_, _>>()?
+            }
+            "DistanceSpacing" => self.distance_spacing = parse_value(key, value)?,
+            "BeatDivisor" => self.beat_divisor = parse_value(key, value)?,
+            "GridSize" => self.grid_size = parse_value(key, value)?,
+            "TimelineZoom" => self.timeline_zoom = parse_value(key, value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub title: String,
+    pub title_unicode: String,
+    pub artist: String,
+    pub artist_unicode: String,
+    pub creator: String,
+    pub version: String,
+    pub source: String,
+    pub tags: Vec<String>,
+    /// `0` for difficulties that have never been uploaded, `None` for old files without the key.
+    pub beatmap_id: Option<i64>,
+    /// `-1` for sets that have never been uploaded, `None` for old files without the key.
+    pub beatmap_set_id: Option<i64>,
+}
+
+impl KeyValueSection for Metadata {
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), ParseErrorKind> {
+        match key {
+            "Title" => self.title = value.to_owned(),
+            "TitleUnicode" => self.title_unicode = value.to_owned(),
+            "Artist" => self.artist = value.to_owned(),
+            "ArtistUnicode" => self.artist_unicode = value.to_owned(),
+            "Creator" => self.creator = value.to_owned(),
+            "Version" => self.version = value.to_owned(),
+            "Source" => self.source = value.to_owned(),
+            "Tags" => self.tags = value.split_whitespace().map(str::to_owned).collect(),
+            "BeatmapID" => self.beatmap_id = Some(parse_value(key, value)?),
+            "BeatmapSetID" => self.beatmap_set_id = Some(parse_value(key, value)?),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difficulty {
+    pub hp_drain_rate: f64,
+    pub circle_size: f64,
+    pub overall_difficulty: f64,
+    /// Older format versions don't have AR, in which case the game uses OD.
+    pub approach_rate: Option<f64>,
+    pub slider_multiplier: f64,
+    pub slider_tick_rate: f64,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            hp_drain_rate: 5.0,
+            circle_size: 5.0,
+            overall_difficulty: 5.0,
+            approach_rate: None,
+            slider_multiplier: 1.4,
+            slider_tick_rate: 1.0,
+        }
+    }
+}
+
+impl KeyValueSection for Difficulty {
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), ParseErrorKind> {
+        match key {
+            "HPDrainRate" => self.hp_drain_rate = parse_value(key, value)?,
+            "CircleSize" => self.circle_size = parse_value(key, value)?,
+            "OverallDifficulty" => self.overall_difficulty = parse_value(key, value)?,
+            "ApproachRate" => self.approach_rate = Some(parse_value(key, value)?),
+            "SliderMultiplier" => self.slider_multiplier = parse_value(key, value)?,
+            "SliderTickRate" => self.slider_tick_rate = parse_value(key, value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colour {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Colour {
+    fn parse(key: &str, value: &str) -> Result<Self, ParseErrorKind> {
+        // Some editors write an alpha channel, which the game ignores.
+        let mut parts = value.split(',').map(str::trim);
+        let mut next = |name: &'static str| {
+            parts
+                .next()
+                .ok_or(ParseErrorKind::MissingField(name))
+                .and_then(|v| parse_value::<u8>(key, v))
+        };
+        Ok(Self {
+            r: next("red")?,
+            g: next("green")?,
+            b: next("blue")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Colours {
+    /// `Combo1` to `Combo8`, in the order they appear.
+    pub combos: Vec<Colour>,
+    pub slider_track_override: Option<Colour>,
+    pub slider_border: Option<Colour>,
+}
+
+impl KeyValueSection for Colours {
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), ParseErrorKind> {
+        match key {
+            _ if key.starts_with("Combo") => self.combos.push(Colour::parse(key, value)?),
+            "SliderTrackOverride" => self.slider_track_override = Some(Colour::parse(key, value)?),
+            "SliderBorder" => self.slider_border = Some(Colour::parse(key, value)?),
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use tokio::{sync::Mutex, time::Instant};
+
+/// A token bucket shared by every in-flight request. It holds up to `burst` tokens and refills
+/// at the configured rate, so short bursts go out at once while the average stays under the
+/// limit.
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+    refill_interval: Duration,
+    burst: u32,
+    acquired: AtomicUsize,
+}
+
+struct Bucket {
+    tokens: u32,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate_limit_per_minute: u64, burst: u32) -> Self {
+        let burst = burst.max(1);
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+            refill_interval: Duration::from_millis(60000 / rate_limit_per_minute.max(1)),
+            burst,
+            acquired: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits for a token. Waiters are served in the order they arrived.
+    pub async fn acquire(&self) {
+        let mut bucket = self.bucket.lock().await;
+        loop {
+            self.refill(&mut bucket);
+            if bucket.tokens > 0 {
+                bucket.tokens -= 1;
+                self.acquired.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            // Holding the lock while asleep keeps later arrivals queued behind this one.
+            tokio::time::sleep_until(bucket.last_refill + self.refill_interval).await;
+        }
+    }
+
+    /// Tokens handed out so far.
+    pub fn acquired(&self) -> usize {
+        self.acquired.load(Ordering::Relaxed)
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        if bucket.tokens == self.burst {
+            bucket.last_refill = Instant::now();
+            return;
+        }
+        let elapsed = bucket.last_refill.elapsed();
+        let refills = u32::try_from(elapsed.as_nanos() / self.refill_interval.as_nanos().max(1))
+            .unwrap_or(u32::MAX);
+        if refills > 0 {
+            bucket.tokens = bucket.tokens.saturating_add(refills).min(self.burst);
+            // A full bucket doesn't carry over leftover time, and can't overflow the interval
+            // multiplication after a very long wait.
+            if bucket.tokens == self.burst {
+                bucket.last_refill = Instant::now();
+            } else {
+                bucket.last_refill += self.refill_interval * refills;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_then_rate() {
+        let limiter = RateLimiter::new(60, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let rested = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(rested.elapsed(), Duration::ZERO);
+        assert_eq!(limiter.acquired(), 8);
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::{Duration, SystemTime};
+
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+
+use crate::rate_limit::RateLimiter;
+
+/// How hard to try before giving up on a request.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    /// Full jitter: a random delay up to `base * 2^attempt`, capped at `max_delay`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        ceiling.mul_f64(fastrand::f64())
+    }
+}
+
+/// Sends the request built by `build`, retrying transient network errors, 429s and 5xxs.
+///
+/// `build` is called once per attempt as reqwest requests can't be reused. Once attempts run
+/// out the last response is returned as is, so callers still see the final status.
+pub async fn send_with_retry<F>(policy: &RetryPolicy, build: F) -> reqwest::Result<Response>
+where
+    F: Fn() -> RequestBuilder,
+{
+    send(policy, None, build).await
+}
+
+/// [`send_with_retry`], taking a token from `limiter` before every attempt so retries count
+/// against the rate limit like any other request.
+pub async fn send_paced_with_retry<F>(
+    policy: &RetryPolicy,
+    limiter: &RateLimiter,
+    build: F,
+) -> reqwest::Result<Response>
+where
+    F: Fn() -> RequestBuilder,
+{
+    send(policy, Some(limiter), build).await
+}
+
+async fn send<F>(
+    policy: &RetryPolicy,
+    limiter: Option<&RateLimiter>,
+    build: F,
+) -> reqwest::Result<Response>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let last_attempt = attempt >= policy.max_attempts;
+        if let Some(limiter) = limiter {
+            limiter.acquire().await;
+        }
+        let delay = match build().send().await {
+            Ok(res) if is_retryable_status(res.status()) && !last_attempt => {
+                let delay = retry_after(&res).unwrap_or_else(|| policy.backoff(attempt));
+                log::warn!(
+                    "Request got {}, retrying in {}ms (attempt {attempt}/{})",
+                    res.status(),
+                    delay.as_millis(),
+                    policy.max_attempts
+                );
+                delay
+            }
+            Err(e) if is_retryable_error(&e) && !last_attempt => {
+                let delay = policy.backoff(attempt);
+                log::warn!(
+                    "Request failed ({e}), retrying in {}ms (attempt {attempt}/{})",
+                    delay.as_millis(),
+                    policy.max_attempts
+                );
+                delay
+            }
+            res => return res,
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn is_retryable_error(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() || e.is_request() || e.is_body()
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn retry_after(res: &Response) -> Option<Duration> {
+    parse_retry_after(
+        res.headers().get(RETRY_AFTER)?.to_str().ok()?,
+        SystemTime::now(),
+    )
+}
+
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:50:07 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::new(10);
+        for attempt in 0..40 {
+            assert!(policy.backoff(attempt) <= policy.max_delay);
+        }
+        assert_eq!(RetryPolicy::new(0).max_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_are_paced() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for status in [
+                "503 Service Unavailable",
+                "503 Service Unavailable",
+                "200 OK",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let client = reqwest::Client::new();
+        let limiter = RateLimiter::new(6000, 10);
+        let res = send_paced_with_retry(&RetryPolicy::new(5), &limiter, || client.get(&url))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(limiter.acquired(), 3);
+    }
+}
@@ -0,0 +1,419 @@
+use std::{
+    collections::HashMap,
+    fs::{File, Metadata},
+    io::{self, BufReader},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    osu_db::{DbBeatmap, OsuDb},
+    osu_file::OsuHeader,
+    song_folder::{LocalDifficulty, Scan, SongFolder},
+};
+
+/// How far a file's modification time may be from the one osu!.db recorded and still count as
+/// the same, as some filesystems only keep it to the even second.
+const MODIFIED_TOLERANCE: Duration = Duration::from_secs(2);
+/// osu!.db's times may be local rather than UTC, which moves them all by the same whole number
+/// of quarter hours.
+const TIME_ZONE_STEP: Duration = Duration::from_secs(15 * 60);
+const MAX_TIME_ZONE_OFFSET: Duration = Duration::from_secs(14 * 60 * 60);
+
+/// Walks a legacy osu! Songs folder, reading the metadata of every .osu in it.
+pub struct SongsScanner {
+    songs_folder: PathBuf,
+    /// (lowercased folder name, lowercased .osu name) -> what `osu!.db` says about it.
+    db_index: HashMap<(String, String), DbBeatmap>,
+    workers: usize,
+}
+
+impl SongsScanner {
+    pub fn new(songs_folder: impl Into<PathBuf>) -> Self {
+        Self {
+            songs_folder: songs_folder.into(),
+            db_index: HashMap::new(),
+            workers: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        }
+    }
+
+    /// How many threads read .osu files at once. Defaults to the number of CPUs.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Takes metadata and MD5s from `db` instead of reading the .osu files it lists. Files it
+    /// doesn't list or that were modified since, e.g. maps added or edited since osu! last
+    /// ran, are still read.
+    pub fn with_osu_db(mut self, db: OsuDb) -> Self {
+        self.db_index = db
+            .beatmaps
+            .into_iter()
+            .map(|beatmap| {
+                let folder = beatmap
+                    .folder_name
+                    .trim_end_matches(['/', '\\'])
+                    .to_lowercase();
+                ((folder, beatmap.osu_file_name.to_lowercase()), beatmap)
+            })
+            .collect();
+        self
+    }
+
+    fn db_entry(&self, dot_osu_file_path: &Path) -> Option<&DbBeatmap> {
+        let file_name = dot_osu_file_path.file_name()?.to_str()?.to_lowercase();
+        let folder = dot_osu_file_path
+            .parent()?
+            .file_name()?
+            .to_str()?
+            .to_lowercase();
+        self.db_index.get(&(folder, file_name))
+    }
+
+    /// Finds every .osu in the song folders and reads the metadata of each. Fails only if the
+    /// Songs folder itself can't be read, song folders and files that can't be are skipped
+    /// with the reason logged.
+    pub fn scan(&self) -> io::Result<Scan> {
+        let start_time = Instant::now();
+        log::info!("Obtaining possible osu! song folders.");
+        let section_start_time = Instant::now();
+        let song_dirs = paths_in_dir_meta_filter(&self.songs_folder, |meta| meta.is_dir())?;
+        let song_dirs_count = song_dirs.len();
+        log::info!("Possible song dirs found: {}", song_dirs_count);
+        log::info!(
+            "Song dirs collected after {}ms ({}ms into the scan)",
+            (Instant::now() - section_start_time).as_millis(),
+            (Instant::now() - start_time).as_millis()
+        );
+        let section_start_time = Instant::now();
+
+        log::info!("Fetching list of files in song folders.");
+        let files_in_song_dirs: Vec<PathBuf> = song_dirs
+            .into_iter()
+            .flat_map(|song_dir| {
+                paths_in_dir_meta_filter(&song_dir, |meta| meta.is_file()).unwrap_or_else(|e| {
+                    log::warn!("Skipping song folder {song_dir:?}, can't read it: {e}");
+                    Vec::new()
+                })
+            })
+            .collect();
+        let files_in_song_dirs_count = files_in_song_dirs.len();
+        log::info!("Files found: {}", files_in_song_dirs_count);
+        log::info!(
+            "Files found after {}ms ({}ms into the scan)",
+            (Instant::now() - section_start_time).as_millis(),
+            (Instant::now() - start_time).as_millis()
+        );
+        let section_start_time = Instant::now();
+
+        log::info!("Filtering to .osu files.");
+        let dot_osu_file_paths: Vec<PathBuf> = files_in_song_dirs
+            .into_iter()
+            .filter(|p| {
+                if let Some(ext) = p.extension() {
+                    ext == "osu"
+                } else {
+                    false
+                }
+            })
+            .collect();
+        log::info!(".osu files found: {}", dot_osu_file_paths.len());
+        log::info!(
+            ".osu files found after {}ms ({}ms into the scan)",
+            (Instant::now() - section_start_time).as_millis(),
+            (Instant::now() - start_time).as_millis()
+        );
+
+        let section_start_time = Instant::now();
+        let mut song_folders: HashMap<PathBuf, SongFolder> = HashMap::new();
+        // .osu path -> its osu!.db entry and the modification time it has now.
+        let db_entries: HashMap<&Path, (&DbBeatmap, Option<SystemTime>)> = dot_osu_file_paths
+            .iter()
+            .filter_map(|path| {
+                let beatmap = self.db_entry(path)?;
+                let modified = std::fs::metadata(path)
+                    .and_then(|meta| meta.modified())
+                    .ok();
+                Some((path.as_path(), (beatmap, modified)))
+            })
+            .collect();
+        let time_zone_offset = infer_time_zone_offset(
+            db_entries
+                .values()
+                .filter_map(|(beatmap, modified)| Some(((*modified)?, beatmap.last_modified?))),
+        );
+        let mut from_db_count = 0;
+        let mut changed_since_db_count = 0;
+        let mut to_read = Vec::new();
+        for dot_osu_file_path in &dot_osu_file_paths {
+            let beatmap = match db_entries.get(dot_osu_file_path.as_path()) {
+                Some((beatmap, modified))
+                    if unchanged_since_db(beatmap, *modified, time_zone_offset) =>
+                {
+                    beatmap
+                }
+                Some(_) => {
+                    changed_since_db_count += 1;
+                    to_read.push(dot_osu_file_path);
+                    continue;
+                }
+                None => {
+                    to_read.push(dot_osu_file_path);
+                    continue;
+                }
+            };
+            from_db_count += 1;
+            add_difficulty(
+                &mut song_folders,
+                LocalDifficulty {
+                    path: dot_osu_file_path.clone(),
+                    metadata: beatmap.metadata(),
+                    md5: Some(beatmap.md5.clone()),
+                    ranked_status: Some(beatmap.ranked_status),
+                    source: None,
+                },
+            );
+        }
+        for difficulty in self.read_difficulties(&to_read).into_iter().flatten() {
+            add_difficulty(&mut song_folders, difficulty);
+        }
+        log::info!(
+            ".osu files read after {}ms ({}ms into the scan)",
+            (Instant::now() - section_start_time).as_millis(),
+            (Instant::now() - start_time).as_millis()
+        );
+        if !self.db_index.is_empty() {
+            log::info!(
+                "{from_db_count} .osu files taken from osu!.db, {} read from disk \
+                 ({changed_since_db_count} of them modified since osu!.db was written)",
+                dot_osu_file_paths.len() - from_db_count
+            );
+        }
+
+        Ok(Scan {
+            song_dirs_count,
+            files_count: files_in_song_dirs_count,
+            osu_files_count: dot_osu_file_paths.len(),
+            song_folders: song_folders.into_values().collect(),
+        })
+    }
+
+    /// Reads `paths` across the worker threads, in the same order. `None` for files that
+    /// couldn't be read, the reason is logged.
+    fn read_difficulties(&self, paths: &[&PathBuf]) -> Vec<Option<LocalDifficulty>> {
+        let next = AtomicUsize::new(0);
+        let read_by_workers: Vec<Vec<(usize, Option<LocalDifficulty>)>> = thread::scope(|s| {
+            let workers: Vec<_> = (0..self.workers.min(paths.len()))
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut read = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(path) = paths.get(i) else {
+                                return read;
+                            };
+                            read.push((i, read_difficulty(path)));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("reading a .osu doesn't panic"))
+                .collect()
+        });
+        let mut difficulties: Vec<Option<LocalDifficulty>> = vec![None; paths.len()];
+        for (i, difficulty) in read_by_workers.into_iter().flatten() {
+            difficulties[i] = difficulty;
+        }
+        difficulties
+    }
+}
+
+fn add_difficulty(song_folders: &mut HashMap<PathBuf, SongFolder>, difficulty: LocalDifficulty) {
+    let folder = difficulty.path.parent().expect("checked").to_owned();
+    song_folders
+        .entry(folder.clone())
+        .or_insert_with(|| SongFolder {
+            path: folder,
+            difficulties: Vec::new(),
+            files: None,
+        })
+        .difficulties
+        .push(difficulty);
+}
+
+/// Parses only the header of the .osu at `path`, leaving the rest of the file unread. It
+/// isn't hashed either, that's only needed for the few the API reports a checksum for, see
+/// [`LocalDifficulty::md5`].
+fn read_difficulty(path: &Path) -> Option<LocalDifficulty> {
+    let read = || OsuHeader::read(&mut BufReader::new(File::open(path)?));
+    match read() {
+        Ok((header, errors)) => {
+            for e in errors {
+                log::warn!("Problem parsing {path:?}, {e}");
+            }
+            Some(LocalDifficulty {
+                path: path.to_owned(),
+                metadata: header.metadata,
+                md5: None,
+                ranked_status: None,
+                source: None,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed reading file {path:?}: {e}");
+            None
+        }
+    }
+}
+
+/// Whether a .osu modified at `modified` still has the modification time osu!.db recorded for
+/// it, i.e. the db's MD5 and details are still the file's. Unrecorded or unreadable times, or
+/// no consistent offset between the two clocks, don't count.
+fn unchanged_since_db(
+    beatmap: &DbBeatmap,
+    modified: Option<SystemTime>,
+    time_zone_offset: Option<i128>,
+) -> bool {
+    match (modified, beatmap.last_modified, time_zone_offset) {
+        (Some(modified), Some(recorded), Some(offset)) => {
+            (signed_millis(modified, recorded) - offset).abs()
+                <= MODIFIED_TOLERANCE.as_millis() as i128
+        }
+        _ => false,
+    }
+}
+
+/// The offset in milliseconds from osu!.db's clock to the files' that most of the
+/// (file time, recorded time) `pairs` agree on. It's the same for every entry, 0 if osu! wrote
+/// UTC and the time zone's offset if it wrote local time, so a file whose times only line up
+/// under some other offset was modified since.
+fn infer_time_zone_offset(pairs: impl Iterator<Item = (SystemTime, SystemTime)>) -> Option<i128> {
+    let tolerance = MODIFIED_TOLERANCE.as_millis() as i128;
+    let step = TIME_ZONE_STEP.as_millis() as i128;
+    let max_offset = MAX_TIME_ZONE_OFFSET.as_millis() as i128;
+    let mut votes: HashMap<i128, usize> = HashMap::new();
+    for (modified, recorded) in pairs {
+        let difference = signed_millis(modified, recorded);
+        let offset = (difference + step / 2).div_euclid(step) * step;
+        if offset.abs() <= max_offset && (difference - offset).abs() <= tolerance {
+            *votes.entry(offset).or_default() += 1;
+        }
+    }
+    votes
+        .into_iter()
+        .max_by_key(|(offset, count)| (*count, -offset.abs(), *offset))
+        .map(|(offset, _)| offset)
+}
+
+/// `a - b` in milliseconds.
+fn signed_millis(a: SystemTime, b: SystemTime) -> i128 {
+    match a.duration_since(b) {
+        Ok(after) => after.as_millis() as i128,
+        Err(e) => -(e.duration().as_millis() as i128),
+    }
+}
+
+/// The entries of `dir` passing `meta_check`. Entries that vanish or can't be read while
+/// listing are skipped with a warning.
+fn paths_in_dir_meta_filter<F>(dir: &Path, meta_check: F) -> io::Result<Vec<PathBuf>>
+where
+    F: Fn(&Metadata) -> bool,
+{
+    let mut res_paths = Vec::new();
+    for p in std::fs::read_dir(dir)? {
+        let meta = p.and_then(|p| Ok((p.metadata()?, p.path())));
+        match meta {
+            Ok((meta, path)) if meta_check(&meta) => res_paths.push(path),
+            Ok(_) => {}
+            Err(e) => log::warn!("Skipping an entry of {dir:?} that can't be read: {e}"),
+        }
+    }
+    Ok(res_paths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_difficulty() {
+        let dir = std::env::temp_dir().join(format!("scanner-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.osu");
+        let contents =
+            "osu file format v14\n[Metadata]\nBeatmapID:1\n[Difficulty]\nCircleSize:4\n[HitObjects]\n256,192,0,1,0\n";
+        std::fs::write(&path, contents).unwrap();
+        let difficulty = read_difficulty(&path).unwrap();
+        let md5 = difficulty.md5();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(difficulty.metadata.beatmap_id, Some(1));
+        // Uploaded or not, hashing waits until something asks.
+        assert_eq!(difficulty.md5, None);
+        assert_eq!(md5, Some(format!("{:x}", md5::compute(contents))));
+    }
+
+    #[test]
+    fn test_infer_time_zone_offset() {
+        let recorded = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let hour = Duration::from_secs(60 * 60);
+        let nine_hours = 9 * hour.as_millis() as i128;
+        assert_eq!(infer_time_zone_offset(std::iter::empty()), None);
+        assert_eq!(
+            infer_time_zone_offset([(recorded + Duration::from_secs(1), recorded)].into_iter()),
+            Some(0)
+        );
+        // Most files agree on the local offset, the odd one out was edited since.
+        let pairs = [
+            (recorded - 9 * hour, recorded),
+            (recorded - 9 * hour + Duration::from_secs(1), recorded),
+            (recorded - hour / 2, recorded),
+        ];
+        assert_eq!(infer_time_zone_offset(pairs.into_iter()), Some(-nine_hours));
+        // Off every quarter hour by more than the tolerance, or beyond any time zone.
+        let pairs = [
+            (recorded + Duration::from_secs(10), recorded),
+            (recorded + hour / 3, recorded),
+            (recorded + 24 * hour, recorded),
+        ];
+        assert_eq!(infer_time_zone_offset(pairs.into_iter()), None);
+    }
+
+    #[test]
+    fn test_unchanged_since_db() {
+        let recorded = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let hour = Duration::from_secs(60 * 60);
+        let beatmap = DbBeatmap {
+            last_modified: Some(recorded),
+            ..Default::default()
+        };
+        let offset = Some(-9 * hour.as_millis() as i128);
+        assert!(unchanged_since_db(
+            &beatmap,
+            Some(recorded - 9 * hour),
+            offset
+        ));
+        assert!(unchanged_since_db(
+            &beatmap,
+            Some(recorded - 9 * hour + Duration::from_secs(1)),
+            offset
+        ));
+        // A whole number of quarter hours off, but not by the offset the rest agree on.
+        assert!(!unchanged_since_db(&beatmap, Some(recorded), offset));
+        assert!(!unchanged_since_db(
+            &beatmap,
+            Some(recorded - 8 * hour),
+            offset
+        ));
+        assert!(!unchanged_since_db(&beatmap, None, offset));
+        assert!(!unchanged_since_db(&beatmap, Some(recorded), None));
+        let unrecorded = DbBeatmap::default();
+        assert!(!unchanged_since_db(&unrecorded, Some(recorded), Some(0)));
+    }
+}
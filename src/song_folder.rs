@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{assets, osu_db::RankedStatus, osu_file::Metadata};
+
+/// One `.osu` file as found on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalDifficulty {
+    pub path: PathBuf,
+    pub metadata: Metadata,
+    /// Only known up front when the source recorded it, see [`Self::md5`].
+    #[serde(default)]
+    pub md5: Option<String>,
+    /// What osu!.db last heard from the server about this exact file, if it was read from there.
+    #[serde(default)]
+    pub ranked_status: Option<RankedStatus>,
+    /// Where to read the file from when `path` isn't a real file, like for lazer's store.
+    #[serde(default)]
+    pub source: Option<PathBuf>,
+}
+
+impl LocalDifficulty {
+    /// The MD5 of the .osu, hashing the file if the scan didn't record one. Scans don't hash
+    /// anything themselves as it's only needed to compare with a checksum from the API.
+    /// `None` if the file can't be read, the reason is logged.
+    pub fn md5(&self) -> Option<String> {
+        if let Some(md5) = &self.md5 {
+            return Some(md5.clone());
+        }
+        let source = self.source.as_ref().unwrap_or(&self.path);
+        match std::fs::read(source) {
+            Ok(contents) => Some(format!("{:x}", md5::compute(contents))),
+            Err(e) => {
+                log::error!("Failed reading file {source:?}: {e}");
+                None
+            }
+        }
+    }
+}
+
+/// A song folder and the difficulties inside it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongFolder {
+    pub path: PathBuf,
+    pub difficulties: Vec<LocalDifficulty>,
+    /// Where the folder's files really live, for sources like lazer's hashed file store where
+    /// `path` isn't a real directory. `None` means everything under `path`.
+    #[serde(default)]
+    pub files: Option<Vec<FolderFile>>,
+}
+
+/// A file belonging to a song folder.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FolderFile {
+    /// Where to read it from.
+    pub source: PathBuf,
+    /// Its path inside the song folder.
+    pub name: PathBuf,
+}
+
+/// Everything the Songs folder scan produced, with the counts for the run summary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scan {
+    pub song_dirs_count: usize,
+    pub files_count: usize,
+    pub osu_files_count: usize,
+    pub song_folders: Vec<SongFolder>,
+}
+
+/// What the `BeatmapSetID:` lines in a folder say about it, before asking the API anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetClassification {
+    /// Every difficulty is marked as never uploaded, the whole folder is unsubmitted.
+    Unsubmitted,
+    /// All difficulties agree on one uploaded set ID.
+    Set(u64),
+    /// Difficulties from more than one uploaded set, e.g. a guest diff copied between sets.
+    Mixed(Vec<u64>),
+    /// No set IDs at all (old format versions), only per-difficulty checks are possible.
+    Unknown,
+}
+
+impl SongFolder {
+    /// Everything in the folder.
+    pub fn files(&self) -> Vec<FolderFile> {
+        if let Some(files) = &self.files {
+            return files.clone();
+        }
+        assets::files_in_dir_recursive(&self.path)
+            .into_iter()
+            .map(|source| FolderFile {
+                name: source
+                    .strip_prefix(&self.path)
+                    .expect("files are inside their folder")
+                    .to_owned(),
+                source,
+            })
+            .collect()
+    }
+
+    /// The uploaded set ID all difficulties agree on, if any.
+    pub fn set_id(&self) -> Option<u64> {
+        match self.set_classification() {
+            SetClassification::Set(set_id) => Some(set_id),
+            _ => None,
+        }
+    }
+
+    pub fn set_classification(&self) -> SetClassification {
+        let never_uploaded = |d: &LocalDifficulty| {
+            d.metadata.beatmap_set_id == Some(-1)
+                || (d.metadata.beatmap_set_id.is_none() && d.metadata.beatmap_id == Some(0))
+        };
+        if !self.difficulties.is_empty() && self.difficulties.iter().all(never_uploaded) {
+            return SetClassification::Unsubmitted;
+        }
+
+        let mut set_ids: Vec<u64> = self
+            .difficulties
+            .iter()
+            .filter_map(|d| d.metadata.beatmap_set_id)
+            .filter(|id| *id > 0)
+            .map(|id| id as u64)
+            .collect();
+        set_ids.sort_unstable();
+        set_ids.dedup();
+        match set_ids.len() {
+            0 => SetClassification::Unknown,
+            1 => SetClassification::Set(set_ids[0]),
+            _ => SetClassification::Mixed(set_ids),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn folder(ids: &[(Option<i64>, Option<i64>)]) -> SongFolder {
+        SongFolder {
+            path: PathBuf::from("song"),
+            difficulties: ids
+                .iter()
+                .map(|(beatmap_id, beatmap_set_id)| LocalDifficulty {
+                    path: PathBuf::from("song/diff.osu"),
+                    metadata: Metadata {
+                        beatmap_id: *beatmap_id,
+                        beatmap_set_id: *beatmap_set_id,
+                        ..Default::default()
+                    },
+                    md5: None,
+                    ranked_status: None,
+                    source: None,
+                })
+                .collect(),
+            files: None,
+        }
+    }
+
+    #[test]
+    fn test_set_classification() {
+        assert_eq!(
+            folder(&[(Some(0), Some(-1)), (Some(0), Some(-1))]).set_classification(),
+            SetClassification::Unsubmitted
+        );
+        assert_eq!(
+            folder(&[(Some(0), None)]).set_classification(),
+            SetClassification::Unsubmitted
+        );
+        assert_eq!(
+            folder(&[(Some(1), Some(5)), (Some(0), Some(5))]).set_classification(),
+            SetClassification::Set(5)
+        );
+        assert_eq!(
+            folder(&[(Some(1), Some(5)), (Some(2), Some(6)), (Some(0), Some(-1))])
+                .set_classification(),
+            SetClassification::Mixed(vec![5, 6])
+        );
+        assert_eq!(
+            folder(&[(Some(1), None)]).set_classification(),
+            SetClassification::Unknown
+        );
+    }
+}
@@ -1,2 +1 @@
-pub(super) mod args;
-pub(super) mod web;
+pub mod web;
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::spec::web::Beatmap;
+
+/// What the API said about one beatmap ID.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "beatmap", rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    Submitted(Beatmap),
+    /// The API answered and the ID wasn't in the response.
+    NotFound,
+    /// The request for this ID failed, so nothing is known about it.
+    Unknown,
+}
+
+/// What the API said about a whole beatmap set.
+pub enum SetStatus {
+    /// The set exists, with these difficulties.
+    Submitted(Vec<Beatmap>),
+    NotFound,
+    /// The request failed, its difficulties have to be asked about one by one.
+    Unknown,
+}
+
+impl SubmissionStatus {
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, SubmissionStatus::Unknown)
+    }
+}
@@ -0,0 +1,193 @@
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+use secrecy::SecretString;
+use tokio::sync::Mutex;
+
+use crate::{
+    retry::{send_with_retry, RetryPolicy},
+    spec::web::{TokenRequest, TokenResponse},
+};
+
+/// Tokens this close to expiring are replaced before use, so a request never races the expiry.
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Hands out a valid access token, fetching a new one when the current one is about to expire
+/// or the API has rejected it.
+pub struct TokenManager {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    /// `None` for a token supplied by the caller, which can't be renewed.
+    credentials: Option<Credentials>,
+    token: Mutex<Token>,
+}
+
+struct Credentials {
+    token_url: Url,
+    client_id: u64,
+    secret: SecretString,
+}
+
+struct Token {
+    access_token: String,
+    token_type: String,
+    /// `None` if the lifetime isn't known.
+    expires_at: Option<Instant>,
+}
+
+impl Token {
+    fn from_response(res: TokenResponse, requested_at: Instant) -> Self {
+        Self {
+            access_token: res.access_token,
+            token_type: res.token_type,
+            expires_at: Some(requested_at + Duration::from_secs(res.expires_in)),
+        }
+    }
+
+    fn needs_refresh(&self, now: Instant) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at.saturating_duration_since(now) < REFRESH_MARGIN)
+    }
+
+    fn authorization(&self) -> String {
+        format!("{} {}", self.token_type, self.access_token)
+    }
+}
+
+impl TokenManager {
+    /// Obtains a client credentials token. `None` if that failed, the reason is logged.
+    pub async fn authenticate(
+        client: reqwest::Client,
+        retry: RetryPolicy,
+        token_url: Url,
+        client_id: u64,
+        secret: &SecretString,
+    ) -> Option<Self> {
+        let credentials = Credentials {
+            token_url,
+            client_id,
+            secret: secret.clone(),
+        };
+        let token = get_token(&client, &retry, &credentials).await?;
+        Some(Self {
+            client,
+            retry,
+            credentials: Some(credentials),
+            token: Mutex::new(token),
+        })
+    }
+
+    /// A token obtained elsewhere. It's used until the API rejects it.
+    pub fn fixed(client: reqwest::Client, retry: RetryPolicy, access_token: String) -> Self {
+        Self {
+            client,
+            retry,
+            credentials: None,
+            token: Mutex::new(Token {
+                access_token,
+                token_type: "Bearer".to_owned(),
+                expires_at: None,
+            }),
+        }
+    }
+
+    /// The `Authorization` header value to send, renewing the token first if it's about to
+    /// expire. If renewing fails the current token is still handed out.
+    pub async fn authorization(&self) -> String {
+        let mut token = self.token.lock().await;
+        if token.needs_refresh(Instant::now()) {
+            if let Some(credentials) = &self.credentials {
+                log::info!("Access token is about to expire, renewing it");
+                if let Some(renewed) = get_token(&self.client, &self.retry, credentials).await {
+                    *token = renewed;
+                }
+            }
+        }
+        token.authorization()
+    }
+
+    /// Replaces a token the API answered 401 to, returning the new header value. Requests
+    /// that were in flight with the same token find it already replaced and don't fetch
+    /// another. `None` if there's no new token to retry with.
+    pub async fn reauthenticate(&self, rejected: &str) -> Option<String> {
+        let mut token = self.token.lock().await;
+        if token.authorization() != rejected {
+            return Some(token.authorization());
+        }
+        let credentials = self.credentials.as_ref()?;
+        log::warn!("Access token was rejected, authenticating again");
+        *token = get_token(&self.client, &self.retry, credentials).await?;
+        Some(token.authorization())
+    }
+}
+
+/// `None` if no token could be obtained, the reason is logged.
+async fn get_token(
+    client: &reqwest::Client,
+    retry: &RetryPolicy,
+    credentials: &Credentials,
+) -> Option<Token> {
+    let body = serde_json::to_string(&TokenRequest::new(
+        credentials.client_id,
+        &credentials.secret,
+    ))
+    .expect("encodes");
+    let requested_at = Instant::now();
+    let res = send_with_retry(retry, || {
+        client
+            .post(credentials.token_url.clone())
+            .body(body.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::ACCEPT, "application/json")
+    })
+    .await;
+    let res = match res {
+        Ok(res) => res,
+        Err(e) => {
+            log::error!("Auth request failed: {e}");
+            return None;
+        }
+    };
+    let status = res.status();
+    let text = match res.text().await {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("Auth response failed: {e}");
+            return None;
+        }
+    };
+    match serde_json::from_str::<TokenResponse>(&text) {
+        Ok(res) => Some(Token::from_response(res, requested_at)),
+        Err(_) => {
+            log::error!("Didn't get creds ({status}), check secret/clientID: {text}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_refresh() {
+        let res: TokenResponse = serde_json::from_str(
+            r#"{"token_type":"Bearer","expires_in":86400,"access_token":"abc"}"#,
+        )
+        .unwrap();
+        let now = Instant::now();
+        let token = Token::from_response(res, now);
+        assert_eq!(token.authorization(), "Bearer abc");
+        assert!(!token.needs_refresh(now));
+        assert!(!token.needs_refresh(now + Duration::from_secs(86400) - REFRESH_MARGIN * 2));
+        assert!(token.needs_refresh(now + Duration::from_secs(86400) - REFRESH_MARGIN / 2));
+        assert!(token.needs_refresh(now + Duration::from_secs(86400 * 2)));
+
+        let fixed = Token {
+            access_token: "abc".to_owned(),
+            token_type: "Bearer".to_owned(),
+            expires_at: None,
+        };
+        assert!(!fixed.needs_refresh(now + Duration::from_secs(86400 * 365)));
+    }
+}